@@ -1,18 +1,28 @@
 use anyhow::Result;
-use crossterm::event;
+use crossterm::event::{self, Event};
 use ratatui::{backend::CrosstermBackend, Terminal};
+use std::collections::HashSet;
 use std::io;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
-use crate::config::merged::AppConfig;
+use crate::config::merged::{reload_processes, AppConfig};
+use crate::config::persist;
+use crate::config::reload::plan_reload;
+use crate::config::watch::ConfigWatcher;
+use crate::control::server::ControlServer;
 use crate::process::manager::ProcessManager;
+use crate::process::outcome::ExitOptions;
 use crate::system::browser::PortKiller;
 use crate::system::killer;
+use crate::system::tree::ProcessTree;
+use crate::tui::dialog::{DialogMode, ProcessDialog};
 use crate::tui::{actions::Action, input::handle_input, renderer::render};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ActiveTab {
     Processes,
+    AllLogs,
     PortKiller,
 }
 
@@ -23,16 +33,70 @@ pub enum Scope {
     TerminalZoomed,
 }
 
+/// A row of the process list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListRow {
+    Group(String),
+    Process(usize),
+}
+
 pub struct UiState {
     pub selected_process: usize,
+    /// A group header is selected: actions apply to the whole group
+    pub selected_group: Option<String>,
+    /// Groups whose processes are hidden in the process list
+    pub collapsed_groups: HashSet<String>,
     pub scope: Scope,
     pub show_keymap: bool,
+    /// Show the details view instead of the output pane
+    pub show_details: bool,
+    /// Show the process tree of the selected process instead of the output pane
+    pub show_tree: bool,
+    /// Show when each output line arrived, left of the output
+    pub show_timestamps: bool,
+    /// Processes left out of the merged log view
+    pub merged_hidden: HashSet<String>,
+    /// Highlighted process in the merged view's filter list
+    pub merged_cursor: usize,
+    /// Lines the merged view is scrolled up from the newest
+    pub merged_scroll: usize,
     /// Mouse selection start (col, row) in absolute terminal coordinates
     pub selection_start: Option<(u16, u16)>,
     /// Mouse selection end (col, row) in absolute terminal coordinates
     pub selection_end: Option<(u16, u16)>,
     /// Ticks remaining to show "copied" flash highlight
     pub copy_flash: u8,
+    /// Short message shown over the bottom right corner
+    pub toast: Option<Toast>,
+}
+
+pub struct Toast {
+    pub message: String,
+    pub error: bool,
+    pub shown_at: Instant,
+}
+
+impl Toast {
+    pub fn info(message: String) -> Self {
+        Self {
+            message,
+            error: false,
+            shown_at: Instant::now(),
+        }
+    }
+
+    pub fn error(message: String) -> Self {
+        Self {
+            message,
+            error: true,
+            shown_at: Instant::now(),
+        }
+    }
+
+    fn expired(&self) -> bool {
+        let shown_for = if self.error { 8 } else { 4 };
+        self.shown_at.elapsed() >= Duration::from_secs(shown_for)
+    }
 }
 
 pub struct App {
@@ -41,42 +105,93 @@ pub struct App {
     pub ui_state: UiState,
     pub process_manager: ProcessManager,
     pub port_killer: PortKiller,
+    pub process_tree: ProcessTree,
     pub auto_exit: bool,
+    pub exit: ExitOptions,
+    /// Serves `betterprocs ctl` commands
+    pub control: Option<ControlServer>,
+    /// Terminal size (cols, rows) the UI is drawn for
+    pub term_size: (u16, u16),
+    /// Running as a background server that clients attach to
+    pub server_mode: bool,
+    /// The attached client asked to detach
+    pub detach_requested: bool,
+    /// Add / edit process dialog, while open
+    pub dialog: Option<ProcessDialog>,
+    /// Config file that dialog changes can be saved to
+    pub config_path: Option<PathBuf>,
+    /// Names of the processes defined in the config file
+    config_processes: HashSet<String>,
+    /// Reloads the config file when it changes
+    config_watcher: Option<ConfigWatcher>,
+    /// Groups selected with `--profile`, applied on reload too
+    profile: Vec<String>,
 }
 
+/// Width of the timestamp gutter ("14:02:11 ")
+pub const TIMESTAMP_GUTTER: u16 = 9;
+
 /// Calculate the output pane dimensions from the total terminal size.
-fn pane_size(term_cols: u16, term_rows: u16) -> (u16, u16) {
+fn pane_size(term_cols: u16, term_rows: u16, timestamps: bool) -> (u16, u16) {
     let list_width = term_cols / 4;
-    let pane_cols = term_cols.saturating_sub(list_width + 2);
+    let gutter = if timestamps { TIMESTAMP_GUTTER } else { 0 };
+    let pane_cols = term_cols.saturating_sub(list_width + 2 + gutter);
     let pane_rows = term_rows.saturating_sub(4);
     (pane_rows.max(1), pane_cols.max(1))
 }
 
 impl App {
     pub fn new(config: AppConfig) -> Self {
-        let (term_cols, term_rows) =
-            crossterm::terminal::size().unwrap_or((80, 24));
-        let (pane_rows, pane_cols) = pane_size(term_cols, term_rows);
+        let term_size = crossterm::terminal::size().unwrap_or((80, 24));
+        let (term_cols, term_rows) = term_size;
+        let (pane_rows, pane_cols) = pane_size(term_cols, term_rows, false);
 
         let mut pm = ProcessManager::new();
         for proc_config in config.processes {
             pm.add_process(proc_config, pane_rows, pane_cols);
         }
 
+        let mut toast = None;
+        let config_watcher = config.config_path.as_ref().and_then(|_| {
+            ConfigWatcher::new(&config.config_files)
+                .map_err(|e| toast = Some(Toast::error(format!("Config reload disabled: {:#}", e))))
+                .ok()
+        });
+
         Self {
             should_quit: false,
             active_tab: ActiveTab::Processes,
             ui_state: UiState {
                 selected_process: 0,
+                selected_group: None,
+                collapsed_groups: HashSet::new(),
                 scope: Scope::ProcessList,
                 show_keymap: true,
+                show_details: false,
+                show_tree: false,
+                show_timestamps: false,
+                merged_hidden: HashSet::new(),
+                merged_cursor: 0,
+                merged_scroll: 0,
                 selection_start: None,
                 selection_end: None,
                 copy_flash: 0,
+                toast,
             },
             process_manager: pm,
             port_killer: PortKiller::new(),
+            process_tree: ProcessTree::new(),
             auto_exit: config.auto_exit,
+            exit: config.exit,
+            control: None,
+            term_size,
+            server_mode: false,
+            detach_requested: false,
+            dialog: None,
+            config_path: config.config_path,
+            config_processes: config.config_processes,
+            config_watcher,
+            profile: config.profile,
         }
     }
 
@@ -85,76 +200,145 @@ impl App {
         terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     ) -> Result<()> {
         loop {
-            self.process_manager.drain_output();
-            self.process_manager.check_autorestart();
-
-            self.ui_state.selected_process = self
-                .process_manager
-                .sort_by_status(self.ui_state.selected_process);
+            self.tick();
+            terminal.draw(|frame| render(frame, self))?;
 
-            if matches!(self.active_tab, ActiveTab::PortKiller) {
-                self.port_killer.maybe_refresh();
+            if event::poll(Duration::from_millis(50))? {
+                let evt = event::read()?;
+                self.handle_event(&evt);
             }
 
-            // Copy flash countdown
-            if self.ui_state.copy_flash > 0 {
-                self.ui_state.copy_flash -= 1;
-                if self.ui_state.copy_flash == 0 {
-                    self.ui_state.selection_start = None;
-                    self.ui_state.selection_end = None;
-                }
+            if self.advance_quit() {
+                break;
             }
+        }
 
-            terminal.draw(|frame| render(frame, self))?;
+        Ok(())
+    }
 
-            if event::poll(Duration::from_millis(50))? {
-                let evt = event::read()?;
-                let action = handle_input(&evt, self);
-                self.dispatch(action);
-            }
+    /// One round of process management and UI bookkeeping, before drawing.
+    pub fn tick(&mut self) {
+        self.process_manager.drain_output();
+        self.process_manager.check_stopping();
+        self.process_manager.check_ready();
+        self.process_manager.check_health();
+        self.process_manager.check_autorestart();
+        self.process_manager.check_watches();
+        self.process_manager.start_pending();
+        self.process_manager.sample_usage();
+        self.check_config_reload();
+        if let Some(ref mut control) = self.control {
+            control.poll(&mut self.process_manager);
+        }
 
-            if self.auto_exit
-                && self.process_manager.process_count() > 0
-                && self.process_manager.all_stopped()
-            {
-                self.should_quit = true;
+        self.ui_state.selected_process = self
+            .process_manager
+            .sort_by_status(self.ui_state.selected_process);
+        if let Some(ref group) = self.ui_state.selected_group {
+            if self.process_manager.group_indices(group).is_empty() {
+                self.ui_state.selected_group = None;
             }
+        }
 
-            if self.should_quit {
-                self.process_manager.stop_all();
-                break;
+        if matches!(self.active_tab, ActiveTab::PortKiller) {
+            self.port_killer.maybe_refresh();
+        } else if self.ui_state.show_tree {
+            let root = self
+                .process_manager
+                .processes
+                .get(self.ui_state.selected_process)
+                .and_then(|h| h.status.pid());
+            self.process_tree.set_root(root);
+            self.process_tree.maybe_refresh();
+        }
+
+        if self.ui_state.toast.as_ref().is_some_and(|t| t.expired()) {
+            self.ui_state.toast = None;
+        }
+
+        // Copy flash countdown
+        if self.ui_state.copy_flash > 0 {
+            self.ui_state.copy_flash -= 1;
+            if self.ui_state.copy_flash == 0 {
+                self.ui_state.selection_start = None;
+                self.ui_state.selection_end = None;
             }
         }
+    }
 
-        Ok(())
+    pub fn handle_event(&mut self, evt: &Event) {
+        let action = handle_input(evt, self);
+        self.dispatch(action);
+    }
+
+    /// Start quitting when it's time, and drive the shutdown. Returns true
+    /// once everything has stopped and the app should exit.
+    pub fn advance_quit(&mut self) -> bool {
+        if (self.auto_exit
+            && self.process_manager.process_count() > 0
+            && self.process_manager.all_stopped())
+            || self.exit.should_stop_all(&self.process_manager)
+        {
+            self.should_quit = true;
+        }
+
+        if self.should_quit {
+            self.dialog = None;
+            // Keep drawing the shutdown screen while processes stop gracefully
+            self.process_manager.begin_shutdown();
+            self.process_manager.advance_shutdown();
+            if self.process_manager.all_stopped() {
+                return true;
+            }
+        }
+        false
     }
 
     fn dispatch(&mut self, action: Action) {
+        // Only quitting again (to force kill) or resizing matters while shutting down
+        if self.should_quit && !matches!(action, Action::Quit | Action::Resize(..)) {
+            return;
+        }
+
         match action {
-            Action::Quit => self.should_quit = true,
+            Action::Quit => {
+                if self.should_quit {
+                    self.process_manager.force_kill_all();
+                }
+                self.should_quit = true;
+            }
             Action::SelectNext => {
                 if matches!(self.active_tab, ActiveTab::PortKiller) {
                     self.port_killer.select_next();
-                } else {
+                } else if matches!(self.active_tab, ActiveTab::AllLogs) {
                     let count = self.process_manager.process_count();
                     if count > 0 {
-                        self.ui_state.selected_process =
-                            (self.ui_state.selected_process + 1) % count;
+                        self.ui_state.merged_cursor = (self.ui_state.merged_cursor + 1) % count;
                     }
+                } else if self.ui_state.show_tree {
+                    self.process_tree.select_next();
+                } else {
+                    self.move_selection(1);
                 }
             }
             Action::SelectPrev => {
                 if matches!(self.active_tab, ActiveTab::PortKiller) {
                     self.port_killer.select_prev();
-                } else {
+                } else if matches!(self.active_tab, ActiveTab::AllLogs) {
                     let count = self.process_manager.process_count();
                     if count > 0 {
-                        if self.ui_state.selected_process == 0 {
-                            self.ui_state.selected_process = count - 1;
-                        } else {
-                            self.ui_state.selected_process -= 1;
-                        }
+                        self.ui_state.merged_cursor =
+                            (self.ui_state.merged_cursor + count - 1) % count;
                     }
+                } else if self.ui_state.show_tree {
+                    self.process_tree.select_prev();
+                } else {
+                    self.move_selection(-1);
+                }
+            }
+            Action::StartProcess if self.ui_state.selected_group.is_some() => {
+                if let Some(ref group) = self.ui_state.selected_group {
+                    self.process_manager.start_group(group);
                 }
             }
             Action::StartProcess => {
@@ -167,6 +351,13 @@ impl App {
                         let _ = killer::kill_process(pid, false);
                         self.port_killer.refresh();
                     }
+                } else if self.ui_state.show_tree {
+                    if let Some(pid) = self.process_tree.selected_pid() {
+                        let _ = killer::kill_process(pid, false);
+                        self.process_tree.refresh();
+                    }
+                } else if let Some(ref group) = self.ui_state.selected_group {
+                    self.process_manager.stop_group(group);
                 } else {
                     let idx = self.ui_state.selected_process;
                     let _ = self.process_manager.stop(idx);
@@ -178,25 +369,75 @@ impl App {
                         let _ = killer::kill_process(pid, true);
                         self.port_killer.refresh();
                     }
+                } else if self.ui_state.show_tree {
+                    if let Some(pid) = self.process_tree.selected_pid() {
+                        let _ = killer::kill_process(pid, true);
+                        self.process_tree.refresh();
+                    }
+                } else if let Some(ref group) = self.ui_state.selected_group {
+                    self.process_manager.force_kill_group(group);
                 } else {
                     let idx = self.ui_state.selected_process;
                     let _ = self.process_manager.force_kill(idx);
                 }
             }
+            Action::RestartProcess if self.ui_state.selected_group.is_some() => {
+                if let Some(ref group) = self.ui_state.selected_group {
+                    self.process_manager.restart_group(group);
+                }
+            }
             Action::RestartProcess => {
                 let idx = self.ui_state.selected_process;
                 let _ = self.process_manager.restart(idx);
             }
+            Action::ClearLogs if matches!(self.active_tab, ActiveTab::AllLogs) => {
+                self.process_manager.merged.clear();
+                self.ui_state.merged_scroll = 0;
+            }
             Action::ClearLogs => {
                 let idx = self.ui_state.selected_process;
                 if let Some(handle) = self.process_manager.processes.get_mut(idx) {
                     handle.screen.clear();
                 }
             }
-            Action::SelectIndex(idx) => {
-                let count = self.process_manager.process_count();
-                if idx < count {
-                    self.ui_state.selected_process = idx;
+            Action::OpenAddDialog => {
+                self.dialog = Some(ProcessDialog::add(self.config_path.is_some()));
+            }
+            Action::OpenEditDialog | Action::RemoveProcess
+                if self.ui_state.selected_group.is_some() => {}
+            Action::OpenEditDialog => {
+                if let Some(handle) = self.process_manager.processes.get(self.ui_state.selected_process) {
+                    self.dialog = Some(ProcessDialog::edit(&handle.config, self.config_path.is_some()));
+                }
+            }
+            Action::RemoveProcess => self.remove_selected_process(),
+            Action::DialogType(c) => {
+                if let Some(ref mut dialog) = self.dialog {
+                    dialog.type_char(c);
+                }
+            }
+            Action::DialogBackspace => {
+                if let Some(ref mut dialog) = self.dialog {
+                    dialog.backspace();
+                }
+            }
+            Action::DialogNextField => {
+                if let Some(ref mut dialog) = self.dialog {
+                    dialog.next_field();
+                }
+            }
+            Action::DialogPrevField => {
+                if let Some(ref mut dialog) = self.dialog {
+                    dialog.prev_field();
+                }
+            }
+            Action::DialogSubmit => self.submit_dialog(),
+            Action::DialogCancel => {
+                self.dialog = None;
+            }
+            Action::SelectRow(row) => {
+                if let Some(row) = self.list_rows().get(row).cloned() {
+                    self.select_row(row);
                     self.ui_state.scope = Scope::ProcessList;
                 }
             }
@@ -223,7 +464,14 @@ impl App {
                 self.ui_state.scope = Scope::ProcessList;
             }
             Action::FocusTerminal => {
-                self.ui_state.scope = Scope::Terminal;
+                match self.ui_state.selected_group {
+                    Some(ref group) => {
+                        if !self.ui_state.collapsed_groups.remove(group) {
+                            self.ui_state.collapsed_groups.insert(group.clone());
+                        }
+                    }
+                    None => self.ui_state.scope = Scope::Terminal,
+                }
             }
             Action::ToggleZoom => {
                 self.ui_state.scope = match self.ui_state.scope {
@@ -231,9 +479,37 @@ impl App {
                     _ => Scope::TerminalZoomed,
                 };
             }
+            Action::ToggleDetails => {
+                self.ui_state.show_details = !self.ui_state.show_details;
+                self.ui_state.show_tree = false;
+            }
+            Action::ToggleProcessTree => {
+                self.ui_state.show_tree = !self.ui_state.show_tree;
+                self.ui_state.show_details = false;
+                // Re-read the tree for whichever process is selected now
+                self.process_tree.set_root(None);
+            }
+            Action::ToggleTimestamps => {
+                self.ui_state.show_timestamps = !self.ui_state.show_timestamps;
+                // The gutter takes columns from the processes' terminals
+                let (w, h) = self.term_size;
+                let (pane_rows, pane_cols) = pane_size(w, h, self.ui_state.show_timestamps);
+                self.process_manager.resize_all(pane_rows, pane_cols);
+            }
             Action::ToggleKeymap => {
                 self.ui_state.show_keymap = !self.ui_state.show_keymap;
             }
+            Action::ScrollUp(n) if matches!(self.active_tab, ActiveTab::AllLogs) => {
+                let max = self
+                    .process_manager
+                    .merged
+                    .visible_count(&self.ui_state.merged_hidden);
+                self.ui_state.merged_scroll =
+                    (self.ui_state.merged_scroll + n as usize).min(max.saturating_sub(1));
+            }
+            Action::ScrollDown(n) if matches!(self.active_tab, ActiveTab::AllLogs) => {
+                self.ui_state.merged_scroll = self.ui_state.merged_scroll.saturating_sub(n as usize);
+            }
             Action::ScrollUp(n) => {
                 if let Some(handle) = self
                     .process_manager
@@ -243,10 +519,8 @@ impl App {
                     handle.screen.scroll_up(n as usize);
                 }
                 // If selecting, extend selection upward
-                if self.ui_state.selection_start.is_some() {
-                    let end = self.ui_state.selection_end.unwrap_or(
-                        self.ui_state.selection_start.unwrap(),
-                    );
+                if let Some(start) = self.ui_state.selection_start {
+                    let end = self.ui_state.selection_end.unwrap_or(start);
                     self.ui_state.selection_end =
                         Some((end.0, end.1.saturating_sub(n)));
                 }
@@ -260,12 +534,9 @@ impl App {
                     handle.screen.scroll_down(n as usize);
                 }
                 // If selecting, extend selection downward
-                if self.ui_state.selection_start.is_some() {
-                    let end = self.ui_state.selection_end.unwrap_or(
-                        self.ui_state.selection_start.unwrap(),
-                    );
-                    let (_, term_rows) =
-                        crossterm::terminal::size().unwrap_or((80, 24));
+                if let Some(start) = self.ui_state.selection_start {
+                    let end = self.ui_state.selection_end.unwrap_or(start);
+                    let (_, term_rows) = self.term_size;
                     self.ui_state.selection_end =
                         Some((end.0, (end.1 + n).min(term_rows.saturating_sub(2))));
                 }
@@ -280,9 +551,15 @@ impl App {
                 }
             }
             Action::Resize(w, h) => {
-                let (pane_rows, pane_cols) = pane_size(w, h);
+                self.term_size = (w, h);
+                let (pane_rows, pane_cols) = pane_size(w, h, self.ui_state.show_timestamps);
                 self.process_manager.resize_all(pane_rows, pane_cols);
             }
+            Action::Detach => {
+                if self.server_mode {
+                    self.detach_requested = true;
+                }
+            }
             Action::SwitchToPortKiller => {
                 self.active_tab = ActiveTab::PortKiller;
                 self.port_killer.refresh();
@@ -290,6 +567,30 @@ impl App {
             Action::SwitchToProcesses => {
                 self.active_tab = ActiveTab::Processes;
             }
+            Action::SwitchToAllLogs => {
+                self.active_tab = ActiveTab::AllLogs;
+                self.ui_state.merged_scroll = 0;
+            }
+            Action::ToggleMergedFilter => {
+                if let Some(name) = self.merged_filter_names().get(self.ui_state.merged_cursor) {
+                    if !self.ui_state.merged_hidden.remove(name) {
+                        self.ui_state.merged_hidden.insert(name.clone());
+                    }
+                }
+                self.ui_state.merged_scroll = 0;
+            }
+            Action::SoloMergedFilter => {
+                let names = self.merged_filter_names();
+                if let Some(solo) = names.get(self.ui_state.merged_cursor) {
+                    self.ui_state.merged_hidden =
+                        names.iter().filter(|n| *n != solo).cloned().collect();
+                }
+                self.ui_state.merged_scroll = 0;
+            }
+            Action::ShowAllMerged => {
+                self.ui_state.merged_hidden.clear();
+                self.ui_state.merged_scroll = 0;
+            }
             Action::PortKillerType(c) => {
                 self.port_killer.type_char(c);
             }
@@ -303,6 +604,201 @@ impl App {
         }
     }
 
+    /// Processes in the merged view's filter list, by name so the order
+    /// doesn't shift as statuses change.
+    pub fn merged_filter_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .process_manager
+            .processes
+            .iter()
+            .map(|h| h.config.name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Rows of the process list: ungrouped processes first, then each group
+    /// (by name) as a header followed by its processes unless collapsed.
+    pub fn list_rows(&self) -> Vec<ListRow> {
+        let processes = &self.process_manager.processes;
+        let mut rows: Vec<ListRow> = (0..processes.len())
+            .filter(|&i| processes[i].config.group.is_none())
+            .map(ListRow::Process)
+            .collect();
+
+        let mut groups: Vec<&str> = processes
+            .iter()
+            .filter_map(|h| h.config.group.as_deref())
+            .collect();
+        groups.sort();
+        groups.dedup();
+        for group in groups {
+            rows.push(ListRow::Group(group.to_string()));
+            if !self.ui_state.collapsed_groups.contains(group) {
+                rows.extend(
+                    (0..processes.len())
+                        .filter(|&i| processes[i].config.group.as_deref() == Some(group))
+                        .map(ListRow::Process),
+                );
+            }
+        }
+        rows
+    }
+
+    /// Position of the selection in `rows`.
+    pub fn selected_row(&self, rows: &[ListRow]) -> usize {
+        let selected = match self.ui_state.selected_group {
+            Some(ref group) => ListRow::Group(group.clone()),
+            None => ListRow::Process(self.ui_state.selected_process),
+        };
+        rows.iter().position(|r| *r == selected).unwrap_or(0)
+    }
+
+    fn select_row(&mut self, row: ListRow) {
+        match row {
+            ListRow::Group(group) => {
+                // Keep showing a process of the group in the output pane
+                if let Some(&first) = self.process_manager.group_indices(&group).first() {
+                    self.ui_state.selected_process = first;
+                }
+                self.ui_state.selected_group = Some(group);
+            }
+            ListRow::Process(index) => {
+                self.ui_state.selected_process = index;
+                self.ui_state.selected_group = None;
+            }
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let rows = self.list_rows();
+        if rows.is_empty() {
+            return;
+        }
+        let len = rows.len() as isize;
+        let next = (self.selected_row(&rows) as isize + delta).rem_euclid(len) as usize;
+        self.select_row(rows[next].clone());
+    }
+
+    /// Reload the config once one of its files has changed, restarting only the
+    /// processes whose command, cwd or env changed. An invalid file leaves
+    /// everything as it was.
+    fn check_config_reload(&mut self) {
+        let changed = self.config_watcher.as_mut().is_some_and(|w| w.poll());
+        if !changed || self.should_quit {
+            return;
+        }
+        let Some(ref path) = self.config_path else {
+            return;
+        };
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let configs = match reload_processes(path, &self.profile) {
+            Ok((configs, files)) => {
+                if let Some(ref mut watcher) = self.config_watcher {
+                    if let Err(e) = watcher.set_files(&files) {
+                        self.ui_state.toast = Some(Toast::error(format!("{:#}", e)));
+                    }
+                }
+                configs
+            }
+            Err(e) => {
+                self.ui_state.toast = Some(Toast::error(format!("Config not reloaded: {:#}", e)));
+                return;
+            }
+        };
+        let names = configs.iter().map(|c| c.name.clone()).collect();
+        let current: Vec<_> = self.process_manager.processes.iter().map(|h| &h.config).collect();
+        let plan = plan_reload(&current, &self.config_processes, configs);
+        self.config_processes = names;
+
+        if let Some(summary) = plan.summary() {
+            self.ui_state.toast = Some(Toast::info(format!("Reloaded {}: {}", file_name, summary)));
+        }
+        let (pane_rows, pane_cols) = self.pane_size();
+        self.process_manager.apply_reload(plan, pane_rows, pane_cols);
+    }
+
+    /// Output pane size for the current terminal size.
+    fn pane_size(&self) -> (u16, u16) {
+        let (w, h) = self.term_size;
+        pane_size(w, h, self.ui_state.show_timestamps)
+    }
+
+    /// Apply the add / edit dialog. Errors keep the dialog open and are
+    /// shown in it; nothing changes unless saving (if asked) succeeded.
+    fn submit_dialog(&mut self) {
+        let Some(mut dialog) = self.dialog.take() else {
+            return;
+        };
+        if let Err(e) = self.apply_dialog(&dialog) {
+            dialog.error = Some(format!("{:#}", e));
+            self.dialog = Some(dialog);
+        }
+    }
+
+    fn apply_dialog(&mut self, dialog: &ProcessDialog) -> Result<()> {
+        let index = match dialog.mode {
+            DialogMode::Add => None,
+            DialogMode::Edit(ref name) => Some(
+                self.process_manager
+                    .index_of(name)
+                    .ok_or_else(|| anyhow::anyhow!("\"{}\" was removed", name))?,
+            ),
+        };
+        let base = match index {
+            Some(i) => self.process_manager.processes[i].config.clone(),
+            None => Default::default(),
+        };
+        let config = dialog.apply(base)?;
+        if index.is_none() && self.process_manager.index_of(&config.name).is_some() {
+            anyhow::bail!("A process named \"{}\" already exists", config.name);
+        }
+        if dialog.save {
+            if let Some(ref path) = self.config_path {
+                persist::save_process(path, &config)?;
+            }
+        }
+
+        match index {
+            Some(i) => {
+                let handle = &mut self.process_manager.processes[i];
+                handle.set_config(config);
+                if handle.status.is_running() {
+                    self.process_manager.restart(i)?;
+                }
+            }
+            None => {
+                let (pane_rows, pane_cols) = self.pane_size();
+                self.process_manager.add_process(config, pane_rows, pane_cols);
+                self.ui_state.selected_process = self.process_manager.process_count() - 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the selected process if it is stopped. Only the running
+    /// session changes; the config file is left alone.
+    fn remove_selected_process(&mut self) {
+        let idx = self.ui_state.selected_process;
+        let Some(handle) = self.process_manager.processes.get_mut(idx) else {
+            return;
+        };
+        let name = handle.config.name.clone();
+        if let Err(e) = self.process_manager.remove_process(idx) {
+            if let Some(handle) = self.process_manager.processes.get_mut(idx) {
+                handle.print_notice(&format!("{:#}", e));
+            }
+            return;
+        }
+        self.ui_state.merged_hidden.remove(&name);
+        let count = self.process_manager.process_count();
+        self.ui_state.selected_process = idx.min(count.saturating_sub(1));
+    }
+
     fn copy_selection_to_clipboard(&self) {
         let (start, end) = match (self.ui_state.selection_start, self.ui_state.selection_end) {
             (Some(s), Some(e)) => (s, e),
@@ -318,9 +814,12 @@ impl App {
 
         // Calculate the output pane offset
         // The pane inner area starts after: process list (25%) + border, status bar + border
-        let (term_cols, _) = crossterm::terminal::size().unwrap_or((80, 24));
+        let (term_cols, _) = self.term_size;
         let list_width = term_cols / 4;
-        let pane_x_offset = list_width + 1; // left border of output pane
+        let mut pane_x_offset = list_width + 1; // left border of output pane
+        if self.ui_state.show_timestamps {
+            pane_x_offset += TIMESTAMP_GUTTER;
+        }
         let pane_y_offset: u16 = 2; // status bar + top border
 
         // Convert terminal coordinates to screen-relative coordinates
@@ -348,7 +847,7 @@ impl App {
                     if contents.is_empty() {
                         text.push(' ');
                     } else {
-                        text.push_str(&contents);
+                        text.push_str(contents);
                     }
                 }
             }
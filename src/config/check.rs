@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use super::merged::{deprecation_warning, validate};
+use super::yaml::{config_path, load_yaml};
+use crate::process::deps::topological_order;
+use crate::process::types::ProcessConfig;
+
+/// Words that can start a shell command without being a program on PATH.
+const SHELL_WORDS: [&str; 22] = [
+    "cd", "exec", "export", "source", ".", "eval", "set", "unset", "trap", "wait", "exit", "true",
+    "false", "test", "[", "if", "for", "while", "until", "case", "(", "{",
+];
+
+/// `betterprocs check`: load the config without starting anything and
+/// report what would go wrong at runtime. Returns the exit code.
+pub fn run(explicit_path: &Option<PathBuf>) -> Result<i32> {
+    let path = config_path(explicit_path)
+        .context("No config file found (betterprocs.yaml, betterprocs.yml or mprocs.yaml)")?;
+    let resolved = load_yaml(&path)?.resolve();
+    let processes = resolved.processes;
+    let mut problems: Vec<String> = resolved.errors.iter().map(|e| format!("{:#}", e)).collect();
+    problems.extend(self::problems(&processes, &resolved.unresolved));
+    if let Some(warning) = deprecation_warning(&processes) {
+        println!("{}: warning: {}", path.display(), warning);
+    }
+
+    if problems.is_empty() {
+        let plural = if processes.len() == 1 { "" } else { "es" };
+        println!(
+            "{}: {} process{}, no problems found",
+            path.display(),
+            processes.len(),
+            plural
+        );
+        return Ok(0);
+    }
+    println!("{}: {} problem(s)", path.display(), problems.len());
+    for problem in &problems {
+        println!("  - {}", problem);
+    }
+    Ok(1)
+}
+
+/// What would go wrong at runtime. The cwd and command of `unresolved`
+/// processes are not checked, as they still hold `${VAR}`s.
+fn problems(processes: &[ProcessConfig], unresolved: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Err(e) = validate(processes) {
+        problems.push(format!("{:#}", e));
+    }
+    if let Err(e) = topological_order(processes) {
+        problems.push(format!("{:#}", e));
+    }
+
+    for process in processes {
+        if unresolved.contains(&process.name) {
+            continue;
+        }
+        if let Some(ref cwd) = process.cwd {
+            if !cwd.is_dir() {
+                problems.push(format!(
+                    "{}: cwd {} does not exist",
+                    process.name,
+                    cwd.display()
+                ));
+                continue;
+            }
+        }
+        if let Some(program) = program(process) {
+            if !program_exists(&program, process) {
+                problems.push(format!(
+                    "{}: command \"{}\" not found",
+                    process.name, program
+                ));
+            }
+        }
+    }
+
+    let mut ports: BTreeMap<u16, Vec<&str>> = BTreeMap::new();
+    for process in processes {
+        if let Some(port) = process.port {
+            ports.entry(port).or_default().push(&process.name);
+        }
+    }
+    for (port, names) in ports {
+        if names.len() > 1 {
+            problems.push(format!("port {} is used by {}", port, names.join(", ")));
+        }
+    }
+    problems
+}
+
+/// The program a process runs: the first word of `cmd`, or of `shell`
+/// after any `VAR=value` assignments. None when that isn't a plain program
+/// name (a shell builtin, or something with quotes or expansions).
+fn program(process: &ProcessConfig) -> Option<String> {
+    if let Some(ref cmd) = process.cmd {
+        return cmd.first().cloned();
+    }
+    let word = process
+        .command
+        .split_whitespace()
+        .find(|word| !word.contains('='))?;
+    if SHELL_WORDS.contains(&word)
+        || word.contains(['\'', '"', '$', '`', '(', ';', '&', '|', '<', '>'])
+    {
+        return None;
+    }
+    Some(word.to_string())
+}
+
+/// Whether the program can be run: a path relative to the process cwd, or
+/// an executable on the PATH the process will see.
+fn program_exists(program: &str, process: &ProcessConfig) -> bool {
+    if program.contains('/') {
+        let cwd = process.cwd.as_deref().unwrap_or(Path::new("."));
+        return cwd.join(program).is_file();
+    }
+    let path_var: Option<OsString> = process
+        .env
+        .get("PATH")
+        .or_else(|| process.file_env.get("PATH"))
+        .map(OsString::from)
+        .or_else(|| std::env::var_os("PATH"));
+    let Some(path_var) = path_var else {
+        return false;
+    };
+    std::env::split_paths(&path_var).any(|dir| {
+        dir.join(program)
+            .metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::yaml::YamlConfig;
+
+    #[test]
+    fn test_program() {
+        assert_eq!(
+            program(&ProcessConfig::test("a", "npm run dev")).as_deref(),
+            Some("npm")
+        );
+        assert_eq!(
+            program(&ProcessConfig::test("a", "RUST_LOG=debug cargo run")).as_deref(),
+            Some("cargo")
+        );
+        assert_eq!(
+            program(&ProcessConfig::test("a", "cd web && npm start")),
+            None
+        );
+        assert_eq!(program(&ProcessConfig::test("a", "$EDITOR notes.md")), None);
+    }
+
+    #[test]
+    fn test_problems() {
+        let api = ProcessConfig {
+            port: Some(8080),
+            ..ProcessConfig::test("api", "sh -c 'exit 0'").depending_on(&["web"])
+        };
+        let web = ProcessConfig {
+            port: Some(8080),
+            ..ProcessConfig::test("web", "no-such-program-xyz --serve").depending_on(&["api"])
+        };
+        let db = ProcessConfig {
+            cwd: Some(PathBuf::from("/no/such/dir")),
+            ..ProcessConfig::test("db", "postgres")
+        };
+
+        let problems = problems(&[api, web, db], &[]);
+        assert_eq!(
+            problems,
+            [
+                "Dependency cycle detected: api -> web -> api",
+                "web: command \"no-such-program-xyz\" not found",
+                "db: cwd /no/such/dir does not exist",
+                "port 8080 is used by api, web",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unresolved() {
+        let config: YamlConfig = serde_yaml::from_str(
+            "procs:\n  api:\n    shell: cargo run\n    cwd: ${BETTERPROCS_NO_SUCH_VAR}\n  \
+             worker:\n    shell: ./worker\n    env_file: /no/such/.env\n  web: 'true'\n",
+        )
+        .unwrap();
+        let resolved = config.resolve();
+        assert_eq!(resolved.processes.len(), 3);
+        assert_eq!(resolved.unresolved, ["api", "worker"]);
+        let errors: Vec<String> = resolved.errors.iter().map(|e| format!("{:#}", e)).collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("Invalid cwd for \"api\""));
+        assert!(errors[1].starts_with("Invalid env_file for \"worker\""));
+        assert!(problems(&resolved.processes, &resolved.unresolved).is_empty());
+    }
+}
@@ -1,9 +1,18 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
+use crate::process::outcome::SuccessPolicy;
+
 #[derive(Parser, Debug)]
-#[command(name = "betterprocs", about = "A better terminal process manager")]
+#[command(
+    name = "betterprocs",
+    about = "A better terminal process manager",
+    args_conflicts_with_subcommands = true
+)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Commands to run (e.g. "npm run dev" "cargo run")
     pub commands: Vec<String>,
 
@@ -19,6 +28,34 @@ pub struct Cli {
     #[arg(long)]
     pub auto_exit: bool,
 
+    /// Stop everything when this process exits and exit with its code
+    #[arg(long, value_name = "NAME")]
+    pub exit_code_from: Option<String>,
+
+    /// Stop everything as soon as a process fails
+    #[arg(long)]
+    pub kill_others_on_fail: bool,
+
+    /// Which exit codes decide betterprocs' own exit code
+    #[arg(long, value_enum)]
+    pub success: Option<SuccessPolicy>,
+
+    /// Only autostart the processes in these groups (comma-separated)
+    #[arg(long, value_delimiter = ',', value_name = "GROUP")]
+    pub profile: Vec<String>,
+
+    /// Run the processes in a background server and attach to it
+    #[arg(long, conflicts_with = "headless")]
+    pub daemon: bool,
+
+    /// Be the background server (started by --daemon)
+    #[arg(long, hide = true)]
+    pub serve: bool,
+
+    /// Run without the TUI, printing `[name]`-prefixed output to stdout
+    #[arg(long)]
+    pub headless: bool,
+
     /// Working directory
     #[arg(long)]
     pub cwd: Option<PathBuf>,
@@ -27,3 +64,48 @@ pub struct Cli {
     #[arg(long, value_delimiter = ',')]
     pub names: Vec<String>,
 }
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Attach to the background server running in the current directory
+    Attach,
+    /// Check the config file for mistakes without starting anything
+    Check {
+        /// Path to config file
+        #[arg(short, long)]
+        config: Option<PathBuf>,
+    },
+    /// Print a JSON Schema of the config file, for editor completion
+    Schema,
+    /// Control the betterprocs instance running in the current directory
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    /// List processes and their status
+    List,
+    /// Show details of one process
+    Status { name: String },
+    /// Start a process (and its dependencies)
+    Start { name: String },
+    /// Stop a process gracefully
+    Stop { name: String },
+    /// Restart a process
+    Restart { name: String },
+    /// Kill a process group with SIGKILL
+    Kill { name: String },
+    /// Type text into a process, followed by Enter
+    SendInput {
+        name: String,
+        text: String,
+        /// Don't press Enter after the text
+        #[arg(long)]
+        no_enter: bool,
+    },
+    /// Clear a process's output
+    Clear { name: String },
+}
@@ -0,0 +1,102 @@
+use anyhow::{bail, Result};
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+/// Parse a human-friendly duration: "250ms", "5s", "2m", "1h" or a bare number of seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let value: f64 = match num.parse() {
+        Ok(v) => v,
+        Err(_) => bail!("Invalid duration \"{}\"", s),
+    };
+    let secs = match unit.trim() {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        other => bail!("Invalid duration unit \"{}\" in \"{}\" (use ms, s, m or h)", other, s),
+    };
+    Ok(Duration::from_secs_f64(secs))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDuration {
+    Seconds(f64),
+    Text(String),
+}
+
+/// Deserialize a duration written as a number of seconds or a string like "500ms".
+pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match RawDuration::deserialize(deserializer)? {
+        RawDuration::Seconds(secs) if secs >= 0.0 => Ok(Duration::from_secs_f64(secs)),
+        RawDuration::Seconds(secs) => Err(serde::de::Error::custom(format!(
+            "duration must not be negative, got {}",
+            secs
+        ))),
+        RawDuration::Text(text) => parse_duration(&text).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Write a duration the way it would be written in a config file, like
+/// "500ms" or "2m". Used for the defaults shown in the JSON Schema.
+pub fn serialize<S>(duration: &Duration, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let ms = duration.as_millis();
+    let text = match ms {
+        0 => "0s".to_string(),
+        _ if ms.is_multiple_of(3_600_000) => format!("{}h", ms / 3_600_000),
+        _ if ms.is_multiple_of(60_000) => format!("{}m", ms / 60_000),
+        _ if ms.is_multiple_of(1000) => format!("{}s", ms / 1000),
+        _ => format!("{}ms", ms),
+    };
+    serializer.serialize_str(&text)
+}
+
+/// JSON Schema for durations, for fields using `deserialize` or `deserialize_opt`.
+pub fn schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "anyOf": [
+            { "type": "number", "minimum": 0 },
+            { "type": "string", "pattern": "^\\s*[0-9.]+\\s*(ms|s|m|h)?\\s*$" }
+        ],
+        "examples": ["500ms", "5s", "2m"]
+    })
+}
+
+/// Like `deserialize`, for `Option<Duration>` fields marked `#[serde(default)]`.
+pub fn deserialize_opt<'de, D>(deserializer: D) -> std::result::Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units() {
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("5s").unwrap(), Duration::from_secs(5));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("1.5").unwrap(), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("5 days").is_err());
+    }
+}
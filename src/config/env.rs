@@ -0,0 +1,246 @@
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::process::types::{ProcessConfig, RawCommand};
+
+/// Variables kept from betterprocs' environment for `clean_env` processes,
+/// so that commands can still be found and run.
+pub const CLEAN_ENV_KEEP: [&str; 5] = ["PATH", "HOME", "USER", "SHELL", "LANG"];
+
+/// The variables a `clean_env` process keeps, with their current values.
+pub fn kept_env() -> Vec<(&'static str, OsString)> {
+    CLEAN_ENV_KEEP
+        .iter()
+        .filter_map(|&key| std::env::var_os(key).map(|value| (key, value)))
+        .collect()
+}
+
+/// `env_file`: one path or a list, later files overriding earlier ones.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum EnvFiles {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+impl EnvFiles {
+    pub fn paths(&self) -> &[PathBuf] {
+        match self {
+            EnvFiles::One(path) => std::slice::from_ref(path),
+            EnvFiles::Many(paths) => paths,
+        }
+    }
+}
+
+/// Read and merge env files in order.
+pub fn load_env_files(paths: &[PathBuf]) -> Result<HashMap<String, String>> {
+    let mut env = HashMap::new();
+    for path in paths {
+        env.extend(load_env_file(path)?);
+    }
+    Ok(env)
+}
+
+fn load_env_file(path: &Path) -> Result<HashMap<String, String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read env file {}", path.display()))?;
+    parse_env_file(&content).with_context(|| format!("Invalid env file {}", path.display()))
+}
+
+/// Parse `KEY=value` lines. Supports comments, blank lines, an `export `
+/// prefix and single or double quoted values (the latter with `\n`, `\"`
+/// and `\\` escapes). Values are not interpolated.
+fn parse_env_file(content: &str) -> Result<HashMap<String, String>> {
+    let mut env = HashMap::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {}: expected KEY=value", i + 1);
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            bail!("line {}: invalid variable name \"{}\"", i + 1, key);
+        }
+        env.insert(key.to_string(), parse_value(value.trim()));
+    }
+    Ok(env)
+}
+
+fn parse_value(value: &str) -> String {
+    if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+        return inner.to_string();
+    }
+    if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        let mut out = String::with_capacity(inner.len());
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            match (c, chars.clone().next()) {
+                ('\\', Some('n')) => out.push('\n'),
+                ('\\', Some(escaped @ ('"' | '\\'))) => out.push(escaped),
+                _ => {
+                    out.push(c);
+                    continue;
+                }
+            }
+            chars.next();
+        }
+        return out;
+    }
+    // Unquoted: a ` #` starts a comment
+    match value.find(" #") {
+        Some(i) => value[..i].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// Replace `${VAR}` and `${VAR:-default}` with values from `lookup`. A
+/// variable without a default must be set; `$${` is a literal `${`. Other
+/// `$` signs are left alone for the shell.
+pub fn interpolate(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if let Some(escaped) = after.strip_prefix("${") {
+            out.push_str("${");
+            rest = escaped;
+            continue;
+        }
+        let Some(expr) = after.strip_prefix('{') else {
+            out.push('$');
+            rest = after;
+            continue;
+        };
+        let Some(end) = expr.find('}') else {
+            bail!("Unclosed \"${{\" in \"{}\"", input);
+        };
+        let (name, default) = match expr[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&expr[..end], None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            bail!("Invalid variable name \"{}\" in \"{}\"", name, input);
+        }
+        // Like the shell, `:-` also replaces an empty value
+        match (lookup(name), default) {
+            (Some(value), Some(default)) if value.is_empty() => out.push_str(default),
+            (Some(value), _) => out.push_str(&value),
+            (None, Some(default)) => out.push_str(default),
+            (None, None) => bail!("Variable {} is not set", name),
+        }
+        rest = &expr[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Resolve `${VAR}` in `raw.env` (from env files and betterprocs'
+/// environment), then in the command and cwd (which also see `env`), and
+/// set the results on `config`. `raw` is kept for editing and saving.
+pub fn interpolate_config(config: &mut ProcessConfig, raw: RawCommand) -> Result<()> {
+    let name = &config.name;
+    let file_env = &config.file_env;
+    let outer = |name: &str| {
+        file_env
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    };
+    let mut env = HashMap::new();
+    for (key, value) in &raw.env {
+        let value = interpolate(value, &outer)
+            .with_context(|| format!("Invalid env {} for \"{}\"", key, name))?;
+        env.insert(key.clone(), value);
+    }
+
+    let lookup = |name: &str| env.get(name).cloned().or_else(|| outer(name));
+    let command = interpolate(&raw.command, &lookup)
+        .with_context(|| format!("Invalid shell for \"{}\"", name))?;
+    let cmd = match raw.cmd {
+        Some(ref args) => Some(
+            args.iter()
+                .map(|arg| interpolate(arg, &lookup))
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("Invalid cmd for \"{}\"", name))?,
+        ),
+        None => None,
+    };
+    let cwd = match raw.cwd {
+        Some(ref cwd) => Some(PathBuf::from(
+            interpolate(&cwd.to_string_lossy(), &lookup)
+                .with_context(|| format!("Invalid cwd for \"{}\"", name))?,
+        )),
+        None => None,
+    };
+
+    config.command = command;
+    config.cmd = cmd;
+    config.cwd = cwd;
+    config.env = env;
+    config.raw = Some(raw);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PORT" => Some("8080".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let interp = |s: &str| interpolate(s, &lookup);
+        assert_eq!(interp("serve --port ${PORT}").unwrap(), "serve --port 8080");
+        assert_eq!(
+            interp("${HOST:-localhost}:${PORT:-3000}").unwrap(),
+            "localhost:8080"
+        );
+        assert_eq!(interp("${EMPTY:-x}").unwrap(), "x");
+        assert_eq!(interp("[${EMPTY}]").unwrap(), "[]");
+        assert_eq!(
+            interp("echo $HOME $${HOME} $").unwrap(),
+            "echo $HOME ${HOME} $"
+        );
+        assert_eq!(
+            interp("${API_KEY}").unwrap_err().to_string(),
+            "Variable API_KEY is not set"
+        );
+        assert!(interp("${PORT").is_err());
+        assert!(interp("${A-B}").is_err());
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        let env = parse_env_file(
+            "# secrets\n\
+             DATABASE_URL=postgres://localhost/dev\n\
+             export TOKEN = abc # comment\n\
+             \n\
+             GREETING=\"hello\\nworld\"\n\
+             RAW='a # b'\n\
+             EMPTY=\n",
+        )
+        .unwrap();
+        assert_eq!(env["DATABASE_URL"], "postgres://localhost/dev");
+        assert_eq!(env["TOKEN"], "abc");
+        assert_eq!(env["GREETING"], "hello\nworld");
+        assert_eq!(env["RAW"], "a # b");
+        assert_eq!(env["EMPTY"], "");
+        assert!(parse_env_file("NOT A LINE").is_err());
+    }
+}
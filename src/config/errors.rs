@@ -0,0 +1,103 @@
+use regex::Regex;
+use std::path::Path;
+
+/// A YAML error as `file:line:column: message`, suggesting the closest
+/// name for an unknown field or value.
+pub fn yaml_error(path: &Path, err: &serde_yaml::Error) -> anyhow::Error {
+    // The location goes in front instead
+    let position = Regex::new(r" at line \d+ column \d+").unwrap();
+    let message = position.replace_all(&err.to_string(), "").into_owned();
+    let message = with_suggestion(&message);
+    match err.location() {
+        Some(at) => anyhow::anyhow!(
+            "{}:{}:{}: {}",
+            path.display(),
+            at.line(),
+            at.column(),
+            message
+        ),
+        None => anyhow::anyhow!("{}: {}", path.display(), message),
+    }
+}
+
+/// Replace serde's list of expected names with the one that was probably
+/// meant, if there is one.
+fn with_suggestion(message: &str) -> String {
+    let unknown = Regex::new(r"unknown (field|variant) `([^`]*)`, expected (.*)$").unwrap();
+    let Some(caps) = unknown.captures(message) else {
+        return message.to_string();
+    };
+    let expected: Vec<&str> = caps[3].split('`').skip(1).step_by(2).collect();
+    match did_you_mean(&caps[2], &expected) {
+        Some(name) => {
+            let start = caps.get(0).unwrap().start();
+            format!(
+                "{}unknown {} `{}`, did you mean `{}`?",
+                &message[..start],
+                &caps[1],
+                &caps[2],
+                name
+            )
+        }
+        None => message.to_string(),
+    }
+}
+
+/// The candidate closest to `input`, if it is close enough to be a typo.
+pub fn did_you_mean<'a>(input: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|&c| (c, strsim::jaro_winkler(input, c)))
+        .filter(|&(_, score)| score >= 0.8)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(c, _)| c)
+}
+
+/// ` (did you mean "x"?)` for a mistyped name, or nothing.
+pub fn suggestion(input: &str, candidates: &[&str]) -> String {
+    match did_you_mean(input, candidates) {
+        Some(name) => format!(" (did you mean \"{}\"?)", name),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(
+            with_suggestion(
+                "procs.api: unknown field `autostrat`, expected one of `shell`, `autostart`, `autorestart`"
+            ),
+            "procs.api: unknown field `autostrat`, did you mean `autostart`?"
+        );
+        assert_eq!(
+            with_suggestion("unknown variant `on-failur`, expected one of `never`, `on-failure`"),
+            "unknown variant `on-failur`, did you mean `on-failure`?"
+        );
+        let unrelated = "unknown field `banana`, expected `paths` or `ignore`";
+        assert_eq!(with_suggestion(unrelated), unrelated);
+        assert_eq!(
+            did_you_mean("frontnd", &["backend", "frontend"]),
+            Some("frontend")
+        );
+    }
+
+    #[test]
+    fn test_yaml_error() {
+        #[derive(Debug, serde::Deserialize)]
+        #[serde(deny_unknown_fields)]
+        #[allow(dead_code)]
+        struct Proc {
+            shell: String,
+            autostart: bool,
+        }
+        let err = serde_yaml::from_str::<Proc>("shell: ls\nautostrat: false\n").unwrap_err();
+        assert_eq!(
+            yaml_error(Path::new("betterprocs.yaml"), &err).to_string(),
+            "betterprocs.yaml:2:1: unknown field `autostrat`, did you mean `autostart`?"
+        );
+    }
+}
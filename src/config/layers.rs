@@ -0,0 +1,357 @@
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::path::{Path, PathBuf};
+
+use super::errors::yaml_error;
+use super::yaml::YamlConfig;
+
+/// Per-developer overlay next to the config file, meant to be git-ignored.
+pub const LOCAL_OVERLAY: &str = "betterprocs.local.yaml";
+
+/// mprocs options betterprocs has no use for. They are dropped from
+/// `mprocs.yaml` so that those files keep working despite the strict
+/// checking of unknown fields.
+const MPROCS_ONLY: [&str; 8] = [
+    "hide_keymap_window",
+    "mouse_scroll_speed",
+    "scrollback",
+    "proc_list_width",
+    "keymap_procs",
+    "keymap_term",
+    "keymap_copy",
+    "server",
+];
+const MPROCS_ONLY_PROC: [&str; 3] = ["add_path", "stop", "scrollback"];
+
+/// `include`: one path or a list, relative to the including file.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Includes {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+/// A config file merged with everything layered around it.
+pub struct Layered {
+    pub value: Value,
+    /// Files that make up the config, including optional ones that don't
+    /// exist yet, so that creating them triggers a reload
+    pub files: Vec<PathBuf>,
+}
+
+/// Read a config file and merge, from lowest to highest precedence: the
+/// user config's `settings`, the file's `include`s (in order, each with its
+/// own includes below it), the file itself, and the local overlay.
+pub fn load_layered(path: &Path) -> Result<Layered> {
+    let mut files = Vec::new();
+    let mut value = Value::Mapping(Mapping::new());
+
+    if let Some(user_path) = user_config_path() {
+        if user_path.exists() {
+            merge(&mut value, read_user_config(&user_path)?);
+        }
+        files.push(user_path);
+    }
+
+    merge(
+        &mut value,
+        read_with_includes(path, &mut Vec::new(), &mut files)?,
+    );
+
+    let local = path.with_file_name(LOCAL_OVERLAY);
+    if local.exists() {
+        merge(
+            &mut value,
+            read_with_includes(&local, &mut Vec::new(), &mut files)?,
+        );
+    } else {
+        files.push(local);
+    }
+
+    Ok(Layered { value, files })
+}
+
+/// `$XDG_CONFIG_HOME/betterprocs/config.yaml`, or `~/.config/...` when
+/// XDG_CONFIG_HOME is not set.
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("betterprocs").join("config.yaml"))
+}
+
+/// Read one file, checking it on its own first so that mistakes are
+/// reported with their line and column.
+fn read_value(path: &Path) -> Result<Value> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut value: Value = serde_yaml::from_str(&content).map_err(|e| yaml_error(path, &e))?;
+    match value {
+        Value::Mapping(_) => {}
+        // An empty file
+        Value::Null => return Ok(Value::Mapping(Mapping::new())),
+        _ => bail!("{} is not a YAML mapping", path.display()),
+    }
+
+    if path.file_name().is_some_and(|name| name == "mprocs.yaml") {
+        strip_mprocs_options(&mut value);
+        // Positions don't survive the stripping
+        YamlConfig::deserialize(value.clone()).map_err(|e| yaml_error(path, &e))?;
+    } else {
+        serde_yaml::from_str::<YamlConfig>(&content).map_err(|e| yaml_error(path, &e))?;
+    }
+    Ok(value)
+}
+
+fn strip_mprocs_options(value: &mut Value) {
+    let Some(config) = value.as_mapping_mut() else {
+        return;
+    };
+    for key in MPROCS_ONLY {
+        config.shift_remove(key);
+    }
+    if let Some(Value::Mapping(procs)) = config.get_mut("procs") {
+        for (_, proc) in procs.iter_mut() {
+            if let Value::Mapping(proc) = proc {
+                for key in MPROCS_ONLY_PROC {
+                    proc.shift_remove(key);
+                }
+            }
+        }
+    }
+}
+
+/// The user config only holds personal settings, never processes.
+fn read_user_config(path: &Path) -> Result<Value> {
+    let value = read_value(path)?;
+    if let Some(key) = value
+        .as_mapping()
+        .and_then(|m| m.keys().find(|k| k.as_str() != Some("settings")))
+    {
+        bail!(
+            "{}: only `settings` can be set in the user config, found `{}`",
+            path.display(),
+            serde_yaml::to_string(key)?.trim_end()
+        );
+    }
+    Ok(value)
+}
+
+/// A file with its includes merged below it. `stack` holds the files being
+/// read, to reject include cycles.
+fn read_with_includes(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<Value> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+        bail!("Include cycle: {}", chain.join(" -> "));
+    }
+
+    let mut value = read_value(path)?;
+    files.push(path.to_path_buf());
+    let includes = take_includes(&mut value)
+        .with_context(|| format!("Invalid include in {}", path.display()))?;
+    // Include paths are relative to the including file
+    let dir = path.parent().unwrap_or(Path::new(""));
+    if !stack.is_empty() {
+        rebase_paths(&mut value, dir);
+    }
+    if includes.is_empty() {
+        return Ok(value);
+    }
+
+    stack.push(canonical);
+    let mut merged = Value::Mapping(Mapping::new());
+    for include in includes {
+        merge(
+            &mut merged,
+            read_with_includes(&dir.join(include), stack, files)
+                .with_context(|| format!("Included from {}", path.display()))?,
+        );
+    }
+    stack.pop();
+    merge(&mut merged, value);
+    Ok(merged)
+}
+
+/// Remove `include` from a file, returning its paths.
+fn take_includes(value: &mut Value) -> Result<Vec<PathBuf>> {
+    let Some(include) = value
+        .as_mapping_mut()
+        .and_then(|m| m.shift_remove("include"))
+    else {
+        return Ok(Vec::new());
+    };
+    Ok(match serde_yaml::from_value(include)? {
+        Includes::One(path) => vec![path],
+        Includes::Many(paths) => paths,
+    })
+}
+
+/// Make the relative `cwd` and `env_file` paths of an included file
+/// relative to where betterprocs runs, like those of the main config, by
+/// prefixing them with the included file's directory. Paths starting with a
+/// `${VAR}` are left alone, as they are only known after interpolation.
+fn rebase_paths(value: &mut Value, dir: &Path) {
+    let Some(config) = value.as_mapping_mut() else {
+        return;
+    };
+    if let Some(Value::Mapping(settings)) = config.get_mut("settings") {
+        if let Some(env_file) = settings.get_mut("env_file") {
+            rebase_path_list(env_file, dir);
+        }
+    }
+    if let Some(Value::Mapping(procs)) = config.get_mut("procs") {
+        for (_, proc) in procs.iter_mut() {
+            let Value::Mapping(proc) = proc else {
+                continue;
+            };
+            if let Some(cwd) = proc.get_mut("cwd") {
+                rebase_path(cwd, dir);
+            }
+            if let Some(env_file) = proc.get_mut("env_file") {
+                rebase_path_list(env_file, dir);
+            }
+        }
+    }
+}
+
+fn rebase_path_list(value: &mut Value, dir: &Path) {
+    match value {
+        Value::Sequence(paths) => paths.iter_mut().for_each(|p| rebase_path(p, dir)),
+        value => rebase_path(value, dir),
+    }
+}
+
+fn rebase_path(value: &mut Value, dir: &Path) {
+    if let Value::String(path) = value {
+        if !path.starts_with('$') && Path::new(path.as_str()).is_relative() {
+            *path = dir.join(path.as_str()).to_string_lossy().into_owned();
+        }
+    }
+}
+
+/// Merge `overlay` into `base`: mappings are merged key by key (new keys
+/// keep their order after the existing ones), anything else in `overlay`
+/// replaces what's in `base`, including lists.
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn yaml(s: &str) -> Value {
+        serde_yaml::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base = yaml(
+            "procs:\n  api:\n    shell: cargo run\n    env: {A: '1', B: '2'}\n    depends_on: [db]\n  db: postgres\n",
+        );
+        merge(
+            &mut base,
+            yaml("procs:\n  api:\n    env: {B: x}\n    depends_on: []\n  web: npm run dev\nsettings:\n  mouse: false\n"),
+        );
+        assert_eq!(
+            base,
+            yaml(
+                "procs:\n  api:\n    shell: cargo run\n    env: {A: '1', B: x}\n    depends_on: []\n  db: postgres\n  web: npm run dev\nsettings:\n  mouse: false\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_includes() {
+        let dir = std::env::temp_dir().join(format!("betterprocs-layers-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        let write = |name: &str, content: &str| std::fs::write(dir.join(name), content).unwrap();
+        write(
+            "shared/base.yaml",
+            "procs:\n  api: cargo run\n  db: postgres\n",
+        );
+        write(
+            "shared/extra.yaml",
+            "include: base.yaml\nprocs:\n  db: docker compose up db\n",
+        );
+        write(
+            "main.yaml",
+            "include: [shared/base.yaml, shared/extra.yaml]\nprocs:\n  api: cargo run --release\n",
+        );
+        write("cycle.yaml", "include: [shared/cycle.yaml]\n");
+        write("shared/cycle.yaml", "include: [../cycle.yaml]\n");
+
+        let mut files = Vec::new();
+        let value =
+            read_with_includes(&dir.join("main.yaml"), &mut Vec::new(), &mut files).unwrap();
+        assert_eq!(
+            value,
+            yaml("procs:\n  api: cargo run --release\n  db: docker compose up db\n")
+        );
+        assert_eq!(files.len(), 4);
+
+        let err = read_with_includes(&dir.join("cycle.yaml"), &mut Vec::new(), &mut Vec::new())
+            .unwrap_err();
+        assert!(err.root_cause().to_string().starts_with("Include cycle: "));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_paths() {
+        let dir = std::env::temp_dir().join(format!(
+            "betterprocs-include-paths-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        std::fs::write(
+            dir.join("shared/base.yaml"),
+            "settings:\n  env_file: common.env\nprocs:\n  worker:\n    shell: ./run\n    cwd: worker\n    env_file: [.env, /etc/worker.env, '${HOME}/.env']\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("main.yaml"),
+            "include: shared/base.yaml\nprocs:\n  web:\n    shell: npm start\n    cwd: web\n",
+        )
+        .unwrap();
+
+        let value =
+            read_with_includes(&dir.join("main.yaml"), &mut Vec::new(), &mut Vec::new()).unwrap();
+        let shared = dir.join("shared").display().to_string();
+        assert_eq!(
+            value,
+            yaml(&format!(
+                "settings:\n  env_file: {0}/common.env\nprocs:\n  worker:\n    shell: ./run\n    cwd: {0}/worker\n    env_file: [{0}/.env, /etc/worker.env, '${{HOME}}/.env']\n  web:\n    shell: npm start\n    cwd: web\n",
+                shared
+            ))
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
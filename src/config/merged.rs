@@ -1,13 +1,27 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 use super::cli::Cli;
 use super::npm::detect_npm_scripts;
-use super::yaml::try_load_yaml;
+use super::yaml::{config_path, load_yaml, try_load_yaml};
+use crate::process::deps::topological_order;
+use crate::process::outcome::ExitOptions;
+use crate::process::restart::RestartPolicy;
 use crate::process::types::ProcessConfig;
 
 pub struct AppConfig {
     pub processes: Vec<ProcessConfig>,
     pub auto_exit: bool,
+    pub exit: ExitOptions,
+    /// YAML file the processes came from, for saving runtime changes
+    pub config_path: Option<PathBuf>,
+    /// Names of the processes defined in `config_path`
+    pub config_processes: HashSet<String>,
+    /// Files the config was merged from: includes, overlays and user config
+    pub config_files: Vec<PathBuf>,
+    /// Groups selected with `--profile`
+    pub profile: Vec<String>,
 }
 
 pub fn load_config(cli: &Cli) -> Result<AppConfig> {
@@ -35,10 +49,16 @@ pub fn load_config(cli: &Cli) -> Result<AppConfig> {
 
     // Source 2: YAML file (if no CLI commands provided)
     let mut auto_exit_from_yaml = false;
+    let mut loaded_path = None;
+    let mut config_processes = HashSet::new();
+    let mut config_files = Vec::new();
     if processes.is_empty() {
-        if let Some(yaml_config) = try_load_yaml(&cli.config)? {
+        if let Some(mut yaml_config) = try_load_yaml(&cli.config)? {
+            loaded_path = config_path(&cli.config);
             auto_exit_from_yaml = yaml_config.auto_exit();
-            processes.extend(yaml_config.into_process_configs());
+            config_files = std::mem::take(&mut yaml_config.files);
+            processes.extend(yaml_config.into_process_configs()?);
+            config_processes = processes.iter().map(|p| p.name.clone()).collect();
         }
     }
 
@@ -64,8 +84,152 @@ pub fn load_config(cli: &Cli) -> Result<AppConfig> {
         );
     }
 
+    validate(&processes)?;
+    // Only to reject cycles and unknown names: processes stay in file
+    // order, and dependents wait for their dependencies when started
+    topological_order(&processes)?;
+    if let Some(warning) = deprecation_warning(&processes) {
+        eprintln!("Warning: {}", warning);
+    }
+    apply_profile(&mut processes, &cli.profile)?;
+
+    let exit = ExitOptions {
+        exit_code_from: cli.exit_code_from.clone(),
+        kill_others_on_fail: cli.kill_others_on_fail,
+        success: cli.success,
+    };
+    if let Some(ref name) = exit.exit_code_from {
+        if !processes.iter().any(|p| &p.name == name) {
+            bail!("--exit-code-from: no process named \"{}\"", name);
+        }
+    }
+
     Ok(AppConfig {
         processes,
-        auto_exit: cli.auto_exit || auto_exit_from_yaml,
+        auto_exit: cli.auto_exit || auto_exit_from_yaml || exit.enabled(),
+        exit,
+        config_path: loaded_path,
+        config_processes,
+        config_files,
+        profile: cli.profile.clone(),
     })
 }
+
+/// Load the processes of a config file again, for hot reloading, along
+/// with the files it is now merged from.
+pub fn reload_processes(
+    path: &Path,
+    profile: &[String],
+) -> Result<(Vec<ProcessConfig>, Vec<PathBuf>)> {
+    let mut yaml_config = load_yaml(path)?;
+    let files = std::mem::take(&mut yaml_config.files);
+    let mut processes = yaml_config.into_process_configs()?;
+    validate(&processes)?;
+    topological_order(&processes)?;
+    apply_profile(&mut processes, profile)?;
+    Ok((processes, files))
+}
+
+/// With a profile, exactly the processes in the selected groups autostart
+/// (their dependencies still start with them).
+fn apply_profile(processes: &mut [ProcessConfig], profile: &[String]) -> Result<()> {
+    if profile.is_empty() {
+        return Ok(());
+    }
+    let mut groups: Vec<&str> = processes.iter().filter_map(|p| p.group.as_deref()).collect();
+    groups.sort();
+    groups.dedup();
+    for name in profile {
+        if !groups.contains(&name.as_str()) {
+            if groups.is_empty() {
+                bail!("--profile: no groups are defined in the config file");
+            }
+            bail!(
+                "--profile: unknown group \"{}\" (groups: {})",
+                name,
+                groups.join(", ")
+            );
+        }
+    }
+    for process in processes {
+        process.autostart = process.group.as_ref().is_some_and(|g| profile.contains(g));
+    }
+    Ok(())
+}
+
+pub fn validate(processes: &[ProcessConfig]) -> Result<()> {
+    for process in processes {
+        if let Some(ref ready) = process.ready {
+            ready
+                .validate()
+                .with_context(|| format!("Invalid ready probe for \"{}\"", process.name))?;
+        }
+        if let Some(ref healthcheck) = process.healthcheck {
+            healthcheck
+                .validate()
+                .with_context(|| format!("Invalid healthcheck for \"{}\"", process.name))?;
+        }
+        if let Some(ref watch) = process.watch {
+            watch
+                .validate()
+                .with_context(|| format!("Invalid watch for \"{}\"", process.name))?;
+        }
+    }
+    Ok(())
+}
+
+/// Settings that still load but are on their way out.
+pub fn deprecation_warning(processes: &[ProcessConfig]) -> Option<String> {
+    let names: Vec<&str> = processes
+        .iter()
+        .filter(|p| p.restart.policy == RestartPolicy::UnlessStopped)
+        .map(|p| p.name.as_str())
+        .collect();
+    if names.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "restart: unless-stopped is deprecated and works like `always` (used by {})",
+        names.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_profile() {
+        let proc = |name: &str, group: Option<&str>| ProcessConfig {
+            name: name.to_string(),
+            group: group.map(String::from),
+            ..Default::default()
+        };
+        let mut processes = vec![
+            proc("web", Some("frontend")),
+            proc("api", Some("backend")),
+            proc("tools", None),
+        ];
+        apply_profile(&mut processes, &["backend".to_string()]).unwrap();
+        let autostart: Vec<bool> = processes.iter().map(|p| p.autostart).collect();
+        assert_eq!(autostart, [false, true, false]);
+
+        let err = apply_profile(&mut processes, &["mobile".to_string()]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--profile: unknown group \"mobile\" (groups: backend, frontend)"
+        );
+    }
+
+    #[test]
+    fn test_deprecation_warning() {
+        let mut api = ProcessConfig::test("api", "cargo run");
+        let web = ProcessConfig::test("web", "npm run dev");
+        assert_eq!(deprecation_warning(&[api.clone(), web.clone()]), None);
+        api.restart.policy = RestartPolicy::UnlessStopped;
+        assert_eq!(
+            deprecation_warning(&[api, web]).unwrap(),
+            "restart: unless-stopped is deprecated and works like `always` (used by api)"
+        );
+    }
+}
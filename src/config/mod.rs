@@ -1,4 +1,14 @@
+pub mod check;
 pub mod cli;
+pub mod duration;
+pub mod env;
+pub mod errors;
+pub mod layers;
 pub mod merged;
 pub mod npm;
+pub mod persist;
+pub mod reload;
+pub mod schema;
+pub mod size;
+pub mod watch;
 pub mod yaml;
@@ -18,13 +18,11 @@ pub fn detect_npm_scripts(dir: &Path) -> Result<Vec<ProcessConfig>> {
 
     let configs: Vec<ProcessConfig> = scripts
         .iter()
-        .filter_map(|(name, _cmd)| {
-            Some(ProcessConfig {
-                name: name.clone(),
-                command: format!("npm run {}", name),
-                autostart: false, // Let user choose which to start
-                ..Default::default()
-            })
+        .map(|(name, _cmd)| ProcessConfig {
+            name: name.clone(),
+            command: format!("npm run {}", name),
+            autostart: false, // Let user choose which to start
+            ..Default::default()
         })
         .collect();
 
@@ -0,0 +1,332 @@
+use anyhow::{bail, Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::process::types::{ProcessConfig, RawCommand};
+
+/// Write a process added or edited at runtime to the config file: its
+/// command, cwd and env, as written before `${VAR}` interpolation. Other
+/// settings of an existing entry are kept.
+///
+/// Only the lines of that process's entry are rewritten, so comments
+/// inside it are lost but the rest of the file is left as it is. Files
+/// this can't be done for, such as one with `procs` written in flow style,
+/// are refused rather than reformatted.
+pub fn save_process(path: &Path, config: &ProcessConfig) -> Result<()> {
+    update_file(path, |content| with_process(content, config))
+}
+
+fn update_file(path: &Path, change: impl FnOnce(&str) -> Result<String>) -> Result<()> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let updated = change(&content).with_context(|| format!("Failed to update {}", path.display()))?;
+    std::fs::write(path, updated).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn with_process(content: &str, config: &ProcessConfig) -> Result<String> {
+    let mut doc: Value = serde_yaml::from_str(content)?;
+    let procs = procs_mut(&mut doc)?;
+    let key = Value::String(config.name.clone());
+    let raw = config.raw_command();
+
+    let entry = match procs.get(&key) {
+        Some(Value::Mapping(entry)) => {
+            let mut entry = entry.clone();
+            set_command(&mut entry, &raw);
+            set_or_remove(&mut entry, "cwd", cwd_value(&raw));
+            set_or_remove(&mut entry, "env", env_value(&raw));
+            Value::Mapping(entry)
+        }
+        _ if raw.cmd.is_none() && raw.cwd.is_none() && raw.env.is_empty() => {
+            raw.command.clone().into()
+        }
+        _ => {
+            let mut entry = Mapping::new();
+            set_command(&mut entry, &raw);
+            set_or_remove(&mut entry, "cwd", cwd_value(&raw));
+            set_or_remove(&mut entry, "env", env_value(&raw));
+            Value::Mapping(entry)
+        }
+    };
+    procs.insert(key.clone(), entry.clone());
+
+    let lines: Vec<&str> = content.lines().collect();
+    let (block, indent) = procs_block(&lines)?;
+    let range = entry_lines(&lines, &block, indent, &key).unwrap_or(block.end..block.end);
+    let mut single = Mapping::new();
+    single.insert(key, entry);
+    let rendered = serde_yaml::to_string(&single)?;
+    let pad = " ".repeat(indent);
+    let new_lines: Vec<String> = rendered
+        .lines()
+        .map(|line| match line {
+            "" => String::new(),
+            line => format!("{}{}", pad, line),
+        })
+        .collect();
+
+    let mut updated: Vec<String> = lines[..range.start].iter().map(|l| l.to_string()).collect();
+    updated.extend(new_lines);
+    updated.extend(lines[range.end..].iter().map(|l| l.to_string()));
+    let newline = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut updated = updated.join(newline);
+    updated.push_str(newline);
+
+    // Catches what editing the text can't handle, like an alias elsewhere
+    // in the file to an anchor in the replaced entry
+    if serde_yaml::from_str::<Value>(&updated).ok().as_ref() != Some(&doc) {
+        bail!(
+            "Can't save \"{}\" without rewriting the rest of the file",
+            config.name
+        );
+    }
+    Ok(updated)
+}
+
+/// The lines holding the entries of a block-style `procs` mapping, ending
+/// after the last non-blank one, and the indentation of its keys.
+fn procs_block(lines: &[&str]) -> Result<(Range<usize>, usize)> {
+    let Some(start) = lines.iter().position(|l| l.starts_with("procs:")) else {
+        bail!("No `procs` mapping in the config file");
+    };
+    let rest = lines[start]["procs:".len()..].trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        bail!("`procs` must be written one process per line to save to it");
+    }
+    let end = (start + 1..lines.len())
+        .find(|&i| !lines[i].is_empty() && !lines[i].starts_with([' ', '\t']))
+        .unwrap_or(lines.len());
+    let end = (start + 1..end)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .map_or(start + 1, |i| i + 1);
+    let indent = lines[start + 1..end]
+        .iter()
+        .find(|l| !is_blank_or_comment(l))
+        .map_or(2, |l| indentation(l));
+    Ok((start + 1..end, indent))
+}
+
+/// The lines of the entry for `key`: its key line and everything indented
+/// below it, up to the last non-blank line.
+fn entry_lines(
+    lines: &[&str],
+    block: &Range<usize>,
+    indent: usize,
+    key: &Value,
+) -> Option<Range<usize>> {
+    let start = block.clone().find(|&i| {
+        !is_blank_or_comment(lines[i])
+            && indentation(lines[i]) == indent
+            && serde_yaml::from_str::<Mapping>(lines[i].trim())
+                .is_ok_and(|m| m.len() == 1 && m.contains_key(key))
+    })?;
+    let next = (start + 1..block.end)
+        .find(|&i| !lines[i].trim().is_empty() && indentation(lines[i]) <= indent)
+        .unwrap_or(block.end);
+    let end = (start + 1..next)
+        .rev()
+        .find(|&i| !lines[i].trim().is_empty())
+        .map_or(start + 1, |i| i + 1);
+    Some(start..end)
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#')
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn procs_mut(doc: &mut Value) -> Result<&mut Mapping> {
+    match doc.get_mut("procs") {
+        Some(Value::Mapping(procs)) => Ok(procs),
+        _ => bail!("No `procs` mapping in the config file"),
+    }
+}
+
+/// `cmd` for a process run without a shell, `shell` otherwise.
+fn set_command(entry: &mut Mapping, raw: &RawCommand) {
+    match raw.cmd {
+        Some(ref args) => {
+            entry.shift_remove("shell");
+            let args: Vec<Value> = args.iter().map(|a| a.clone().into()).collect();
+            entry.insert("cmd".into(), Value::Sequence(args));
+        }
+        None => {
+            entry.shift_remove("cmd");
+            entry.insert("shell".into(), raw.command.clone().into());
+        }
+    }
+}
+
+fn set_or_remove(entry: &mut Mapping, key: &str, value: Option<Value>) {
+    match value {
+        Some(value) => {
+            entry.insert(key.into(), value);
+        }
+        None => {
+            entry.shift_remove(key);
+        }
+    }
+}
+
+fn cwd_value(raw: &RawCommand) -> Option<Value> {
+    raw.cwd.as_ref().map(|cwd| cwd.display().to_string().into())
+}
+
+fn env_value(raw: &RawCommand) -> Option<Value> {
+    if raw.env.is_empty() {
+        return None;
+    }
+    let mut keys: Vec<&String> = raw.env.keys().collect();
+    keys.sort();
+    let env: Mapping = keys
+        .into_iter()
+        .map(|k| (k.clone().into(), raw.env[k].clone().into()))
+        .collect();
+    Some(Value::Mapping(env))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::env::interpolate_config;
+    use std::path::PathBuf;
+
+    const CONFIG: &str = "\
+procs:
+  api:
+    shell: cargo run
+    port: 8080
+  web: npm run dev
+settings:
+  auto_exit: false
+";
+
+    #[test]
+    fn test_add_and_edit() {
+        let migrate = ProcessConfig {
+            name: "migrate".to_string(),
+            command: "npm run migrate".to_string(),
+            ..Default::default()
+        };
+        let out = with_process(CONFIG, &migrate).unwrap();
+        assert!(out.contains("migrate: npm run migrate"));
+        assert!(out.contains("auto_exit: false"));
+
+        let api = ProcessConfig {
+            name: "api".to_string(),
+            command: "cargo run --release".to_string(),
+            cwd: Some(PathBuf::from("backend")),
+            env: [("RUST_LOG".to_string(), "debug".to_string())].into(),
+            ..Default::default()
+        };
+        let out = with_process(CONFIG, &api).unwrap();
+        let doc: Value = serde_yaml::from_str(&out).unwrap();
+        let entry = &doc["procs"]["api"];
+        assert_eq!(entry["shell"], "cargo run --release");
+        assert_eq!(entry["port"], 8080);
+        assert_eq!(entry["cwd"], "backend");
+        assert_eq!(entry["env"]["RUST_LOG"], "debug");
+
+        let api = ProcessConfig {
+            name: "api".to_string(),
+            cmd: Some(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "echo hi".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let doc: Value = serde_yaml::from_str(&with_process(CONFIG, &api).unwrap()).unwrap();
+        let entry = &doc["procs"]["api"];
+        assert_eq!(entry["cmd"][2], "echo hi");
+        assert!(entry.get("shell").is_none());
+    }
+
+    #[test]
+    fn test_saves_uninterpolated_values() {
+        let mut api = ProcessConfig {
+            name: "api".to_string(),
+            file_env: [("DATABASE_URL".to_string(), "postgres://db".to_string())].into(),
+            ..Default::default()
+        };
+        let raw = RawCommand {
+            command: "serve --port ${PORT:-8080}".to_string(),
+            env: [("DATABASE_URL".to_string(), "${DATABASE_URL}".to_string())].into(),
+            ..Default::default()
+        };
+        interpolate_config(&mut api, raw).unwrap();
+        assert_eq!(api.env["DATABASE_URL"], "postgres://db");
+
+        let out = with_process(CONFIG, &api).unwrap();
+        assert!(!out.contains("postgres://"));
+        let doc: Value = serde_yaml::from_str(&out).unwrap();
+        assert_eq!(doc["procs"]["api"]["shell"], "serve --port ${PORT:-8080}");
+        assert_eq!(
+            doc["procs"]["api"]["env"]["DATABASE_URL"],
+            "${DATABASE_URL}"
+        );
+    }
+
+    #[test]
+    fn test_keeps_the_rest_of_the_file() {
+        let content = "\
+# Shared dev setup
+procs:
+  # The API server
+  api:
+    shell: cargo run  # debug build
+    port: 8080
+
+  web: npm run dev   # frontend
+
+settings:
+  auto_exit: false
+";
+        let api = ProcessConfig {
+            cwd: Some(PathBuf::from("backend")),
+            ..ProcessConfig::test("api", "cargo run --release")
+        };
+        assert_eq!(
+            with_process(content, &api).unwrap(),
+            content.replace(
+                "    shell: cargo run  # debug build\n    port: 8080\n",
+                "    shell: cargo run --release\n    port: 8080\n    cwd: backend\n"
+            )
+        );
+
+        let worker = ProcessConfig::test("worker", "npm run worker");
+        assert_eq!(
+            with_process(content, &worker).unwrap(),
+            content.replace("# frontend\n", "# frontend\n  worker: npm run worker\n")
+        );
+    }
+
+    #[test]
+    fn test_refuses_to_reformat() {
+        let api = ProcessConfig::test("api", "cargo run --release");
+        assert!(with_process("procs: {api: cargo run}\n", &api).is_err());
+        let aliased = "procs:\n  api: &api\n    shell: cargo run\n  api2: *api\n";
+        let err = with_process(aliased, &api).unwrap_err();
+        assert!(err.to_string().starts_with("Can't save \"api\""));
+    }
+
+    #[test]
+    fn test_no_procs() {
+        let config = ProcessConfig {
+            name: "x".to_string(),
+            command: "true".to_string(),
+            ..Default::default()
+        };
+        assert!(with_process("settings: {}\n", &config).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use crate::process::types::ProcessConfig;
+
+/// What to change in the running processes after the config file changed.
+#[derive(Debug, Default)]
+pub struct ReloadPlan {
+    /// New processes
+    pub added: Vec<ProcessConfig>,
+    /// Processes that are no longer in the file
+    pub removed: Vec<String>,
+    /// Processes whose command, cwd or environment changed: restart them if running
+    pub changed: Vec<ProcessConfig>,
+    /// Processes with only other settings changed (or none), which take
+    /// effect on their next start
+    pub updated: Vec<ProcessConfig>,
+}
+
+impl ReloadPlan {
+    /// Short description of what the reload does, or None if it changes
+    /// no process.
+    pub fn summary(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            (self.added.len(), "added"),
+            (self.removed.len(), "removed"),
+            (self.changed.len(), "changed"),
+        ]
+        .into_iter()
+        .filter(|&(n, _)| n > 0)
+        .map(|(n, what)| format!("{} {}", n, what))
+        .collect();
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(", "))
+        }
+    }
+}
+
+/// Compare the processes in the reloaded file with the running ones.
+/// Only processes that came from the file (`from_file`) are removed when
+/// they disappear from it; ones added at runtime or from package.json stay.
+pub fn plan_reload(
+    current: &[&ProcessConfig],
+    from_file: &HashSet<String>,
+    configs: Vec<ProcessConfig>,
+) -> ReloadPlan {
+    let mut plan = ReloadPlan::default();
+    let names: HashSet<&str> = configs.iter().map(|c| c.name.as_str()).collect();
+    plan.removed = current
+        .iter()
+        .map(|c| &c.name)
+        .filter(|name| from_file.contains(*name) && !names.contains(name.as_str()))
+        .cloned()
+        .collect();
+
+    for config in configs {
+        match current.iter().find(|c| c.name == config.name) {
+            None => plan.added.push(config),
+            Some(old) if launch_changed(old, &config) => plan.changed.push(config),
+            Some(_) => plan.updated.push(config),
+        }
+    }
+    plan
+}
+
+/// Whether the process would be started differently.
+fn launch_changed(old: &ProcessConfig, new: &ProcessConfig) -> bool {
+    old.command != new.command
+        || old.cmd != new.cmd
+        || old.cwd != new.cwd
+        || old.env != new.env
+        || old.file_env != new.file_env
+        || old.clean_env != new.clean_env
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_reload() {
+        let api = ProcessConfig::test("api", "cargo run");
+        let web = ProcessConfig::test("web", "npm run dev");
+        let db = ProcessConfig::test("db", "postgres");
+        let lint = ProcessConfig::test("lint", "npm run lint");
+        let current = [&api, &web, &db, &lint];
+        // lint came from package.json
+        let from_file: HashSet<String> = ["api", "web", "db"].map(String::from).into();
+
+        let mut web2 = ProcessConfig::test("web", "npm run dev");
+        web2.port = Some(3000);
+        let mut api2 = ProcessConfig::test("api", "cargo run");
+        api2.env.insert("RUST_LOG".to_string(), "debug".to_string());
+        let plan = plan_reload(
+            &current,
+            &from_file,
+            vec![api2, web2, ProcessConfig::test("worker", "npm run worker")],
+        );
+
+        let names = |configs: &[ProcessConfig]| -> Vec<String> {
+            configs.iter().map(|c| c.name.clone()).collect()
+        };
+        assert_eq!(names(&plan.added), ["worker"]);
+        assert_eq!(plan.removed, ["db"]);
+        assert_eq!(names(&plan.changed), ["api"]);
+        assert_eq!(names(&plan.updated), ["web"]);
+        assert_eq!(plan.summary().unwrap(), "1 added, 1 removed, 1 changed");
+    }
+
+    #[test]
+    fn test_unchanged_file() {
+        let api = ProcessConfig::test("api", "cargo run");
+        let from_file: HashSet<String> = ["api".to_string()].into();
+        let plan = plan_reload(
+            &[&api],
+            &from_file,
+            vec![ProcessConfig::test("api", "cargo run")],
+        );
+        assert!(plan.summary().is_none());
+        assert_eq!(plan.updated.len(), 1);
+    }
+}
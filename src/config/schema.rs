@@ -0,0 +1,44 @@
+use anyhow::Result;
+use schemars::{schema_for, Schema};
+
+use super::yaml::YamlConfig;
+
+/// JSON Schema of the config file, derived from the structs it is parsed
+/// into so that the two can't drift apart.
+pub fn config_schema() -> Schema {
+    schema_for!(YamlConfig)
+}
+
+/// `betterprocs schema`: print the schema for editors and YAML language
+/// servers.
+pub fn run() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&config_schema())?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_config_schema() {
+        let schema = config_schema().to_value();
+        assert_eq!(schema["additionalProperties"], false);
+        let process = &schema["$defs"]["Process"];
+        assert_eq!(process["additionalProperties"], false);
+        assert!(process["properties"]["autostart"]["description"].is_string());
+
+        let restart = serde_json::to_string(&schema["$defs"]["RestartPolicy"]).unwrap();
+        for policy in ["never", "on-failure", "always", "unless-stopped"] {
+            assert!(restart.contains(policy), "{} missing", policy);
+        }
+
+        // Every key of the example config is known to the schema
+        let example: Value =
+            serde_yaml::from_str(include_str!("../../betterprocs.yaml.example")).unwrap();
+        for key in example.as_object().unwrap().keys() {
+            assert!(schema["properties"].get(key).is_some(), "{} missing", key);
+        }
+    }
+}
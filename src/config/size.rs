@@ -0,0 +1,77 @@
+use anyhow::{bail, Result};
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer};
+
+/// Parse a human-friendly byte size: "512K", "10MB", "1G" or a bare number of bytes.
+/// Units are powers of 1024; a trailing "B" / "iB" is optional.
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (num, unit) = s.split_at(split);
+    let value: f64 = match num.parse() {
+        Ok(v) => v,
+        Err(_) => bail!("Invalid size \"{}\"", s),
+    };
+    let unit = unit.trim().to_uppercase();
+    let unit = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    let factor: u64 = match unit {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        other => bail!("Invalid size unit \"{}\" in \"{}\" (use K, M or G)", other, s),
+    };
+    Ok((value * factor as f64) as u64)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSize {
+    Bytes(u64),
+    Text(String),
+}
+
+/// Deserialize an optional size written as a number of bytes or a string like "10MB".
+pub fn deserialize_opt<'de, D>(deserializer: D) -> std::result::Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<RawSize>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(RawSize::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(RawSize::Text(text)) => parse_size(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// JSON Schema for sizes, for fields using `deserialize_opt`.
+pub fn schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "anyOf": [
+            { "type": "integer", "minimum": 0 },
+            { "type": "string", "pattern": "^\\s*[0-9.]+\\s*([KMGkmg]([iI]?[bB])?|[bB])?\\s*$" }
+        ],
+        "examples": ["512K", "10MB", "1G"]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("4K").unwrap(), 4096);
+        assert_eq!(parse_size("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size("1.5GiB").unwrap(), 3 * 512 * 1024 * 1024);
+        assert!(parse_size("big").is_err());
+        assert!(parse_size("5TB").is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+/// Quiet time after the last change before reporting it. Editors often
+/// save in several steps (truncate and write, or write and rename).
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// Notices changes to the config files. Watches their directories rather
+/// than the files themselves, so saves that replace a file are seen too, as
+/// are files that are created later (like a local overlay).
+pub struct ConfigWatcher {
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+    events: Receiver<notify::Result<notify::Event>>,
+    changed_at: Option<Instant>,
+    watcher: RecommendedWatcher,
+}
+
+impl ConfigWatcher {
+    pub fn new(files: &[PathBuf]) -> Result<Self> {
+        let (tx, events) = channel();
+        let watcher = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+        let mut config_watcher = Self {
+            files: HashSet::new(),
+            dirs: HashSet::new(),
+            events,
+            changed_at: None,
+            watcher,
+        };
+        config_watcher.set_files(files)?;
+        Ok(config_watcher)
+    }
+
+    /// Watch these files from now on (after a reload changed the includes).
+    /// Files in directories that don't exist are skipped.
+    pub fn set_files(&mut self, files: &[PathBuf]) -> Result<()> {
+        self.files.clear();
+        for file in files {
+            let (Some(dir), Some(name)) = (file.parent(), file.file_name()) else {
+                continue;
+            };
+            let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+            let Ok(dir) = dir.canonicalize() else {
+                continue;
+            };
+            if !self.dirs.contains(&dir) {
+                self.watcher
+                    .watch(&dir, RecursiveMode::NonRecursive)
+                    .with_context(|| format!("Failed to watch {}", dir.display()))?;
+                self.dirs.insert(dir.clone());
+            }
+            self.files.insert(dir.join(name));
+        }
+        Ok(())
+    }
+
+    /// True once a file has changed and then stayed unchanged for a moment.
+    pub fn poll(&mut self) -> bool {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else { continue };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            if event.paths.iter().any(|p| self.files.contains(p)) {
+                self.changed_at = Some(Instant::now());
+            }
+        }
+        match self.changed_at {
+            Some(at) if at.elapsed() >= SETTLE => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -1,16 +1,47 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use indexmap::IndexMap;
-use serde::Deserialize;
+use schemars::JsonSchema;
+use nix::sys::signal::Signal;
+use serde::de::{self, value::MapAccessDeserializer, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
 use std::collections::HashMap;
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use super::duration;
+use super::env::{interpolate_config, load_env_files, EnvFiles};
+use super::errors::suggestion;
+use super::layers::{load_layered, Includes};
+
+use crate::process::log::LogOption;
+use crate::process::probe::{HealthcheckConfig, ProbeConfig};
+use crate::process::restart::{self, RestartConfig, RestartPolicy};
+use crate::process::signal::{default_stop_signals, deserialize_signals, signals_schema};
+use crate::process::types::default_stop_timeout;
 use crate::process::types::ProcessConfig;
+use crate::process::watch::WatchOption;
 
-#[derive(Debug, Deserialize)]
+/// A betterprocs config file (`betterprocs.yaml`).
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(title = "betterprocs config")]
 pub struct YamlConfig {
+    /// Files merged below this one, relative to it
+    #[serde(default)]
+    #[allow(dead_code)]
+    pub include: Option<Includes>,
+    /// Processes by name. May be left out of included files and overlays.
+    #[serde(default)]
     pub procs: IndexMap<String, YamlProcEntry>,
+    /// Named groups of processes, for `--profile` and group actions
+    #[serde(default)]
+    pub groups: IndexMap<String, Vec<String>>,
     #[serde(default)]
     pub settings: YamlSettings,
+    /// Every file the config was merged from, for watching
+    #[serde(skip)]
+    pub files: Vec<PathBuf>,
 }
 
 impl YamlConfig {
@@ -19,89 +50,357 @@ impl YamlConfig {
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
+#[derive(Debug, JsonSchema)]
+#[schemars(untagged, rename = "ProcessEntry")]
 pub enum YamlProcEntry {
     /// Simple string form: "npm run dev"
     Simple(String),
     /// Full config form
-    Full(YamlProcConfig),
+    Full(Box<YamlProcConfig>),
+}
+
+/// Picks the form by the YAML type rather than trying both (as `untagged`
+/// would), so mistakes in the full form are reported where they are.
+impl<'de> Deserialize<'de> for YamlProcEntry {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct EntryVisitor;
+
+        impl<'de> Visitor<'de> for EntryVisitor {
+            type Value = YamlProcEntry;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a command string or a process config")
+            }
+
+            fn visit_str<E: de::Error>(self, cmd: &str) -> std::result::Result<Self::Value, E> {
+                Ok(YamlProcEntry::Simple(cmd.to_string()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let config = YamlProcConfig::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(YamlProcEntry::Full(Box::new(config)))
+            }
+        }
+
+        deserializer.deserialize_any(EntryVisitor)
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(rename = "Process")]
 pub struct YamlProcConfig {
+    /// Command line run with `sh -c`
     pub shell: Option<String>,
+    /// Program and arguments, run without a shell
     pub cmd: Option<Vec<String>>,
+    /// Working directory
     pub cwd: Option<PathBuf>,
+    /// Environment variables; values may use `${VAR}` and `${VAR:-default}`
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Start with betterprocs (`--profile` can override this)
     #[serde(default = "default_true")]
     pub autostart: bool,
+    /// Restart policy: never, on-failure, always or unless-stopped
+    pub restart: Option<RestartPolicy>,
+    /// Legacy mprocs flag, same as `restart: always`
     #[serde(default)]
     pub autorestart: bool,
+    /// Initial restart backoff, doubled on every restart within `restart_window`
+    #[serde(default = "restart::default_delay", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    pub restart_delay: Duration,
+    /// Upper limit for the restart backoff
+    #[serde(default = "restart::default_max_delay", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    pub restart_max_delay: Duration,
+    /// Restarts allowed within `restart_window` before giving up (crash loop)
+    #[serde(default = "restart::default_max_restarts")]
+    pub max_restarts: u32,
+    /// Time after which earlier restarts no longer count
+    #[serde(default = "restart::default_window", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    pub restart_window: Duration,
+    /// Port the process listens on, checked for conflicts before starting
     pub port: Option<u16>,
+    /// Processes that must be ready before this one is started
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Probe that decides when the process is ready (tcp, http, log or command)
+    pub ready: Option<ProbeConfig>,
+    /// Periodic health check once the process is up
+    pub healthcheck: Option<HealthcheckConfig>,
+    /// Stop signal (TERM, INT, QUIT, HUP, ...) or a sequence to escalate through
+    #[serde(
+        default = "default_stop_signals",
+        deserialize_with = "deserialize_signals"
+    )]
+    #[schemars(schema_with = "signals_schema")]
+    pub stop_signal: Vec<Signal>,
+    /// How long to wait after each stop signal before escalating
+    #[serde(default = "default_stop_timeout", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    pub stop_timeout: Duration,
+    /// Stopped earlier on quit than lower priorities; ties follow dependency order
+    #[serde(default)]
+    pub shutdown_priority: i32,
+    /// Log file for this process: true, false or settings overriding `settings.log`
+    pub log: Option<LogOption>,
+    /// Restart when files change: glob patterns, or `paths`, `ignore` and `debounce`
+    pub watch: Option<WatchOption>,
+    /// Env files loaded after the global ones; `env` still wins over them
+    pub env_file: Option<EnvFiles>,
+    /// Start without betterprocs' environment (overrides `settings.clean_env`)
+    pub clean_env: Option<bool>,
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(rename = "Settings")]
 #[allow(dead_code)]
 pub struct YamlSettings {
+    /// Quit when all processes have stopped
     #[serde(default)]
     pub auto_exit: bool,
     pub mouse: Option<bool>,
     pub scrollback: Option<usize>,
+    /// Default log file settings for every process
+    pub log: Option<LogOption>,
+    /// Env files for every process
+    pub env_file: Option<EnvFiles>,
+    /// Start processes without betterprocs' environment, apart from a few
+    /// essentials like PATH and HOME
+    #[serde(default)]
+    pub clean_env: bool,
 }
 
 fn default_true() -> bool {
     true
 }
 
+/// Load a config file along with its includes, the user config and the
+/// local overlay (see `layers::load_layered`).
 pub fn load_yaml(path: &Path) -> Result<YamlConfig> {
-    let content =
-        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
-    let config: YamlConfig =
-        serde_yaml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    let layered = load_layered(path)?;
+    let mut config: YamlConfig = serde_yaml::from_value(layered.value)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    config.files = layered.files;
     Ok(config)
 }
 
 pub fn try_load_yaml(explicit_path: &Option<PathBuf>) -> Result<Option<YamlConfig>> {
-    // If explicit path given, it must exist
-    if let Some(path) = explicit_path {
-        return Ok(Some(load_yaml(path)?));
+    match config_path(explicit_path) {
+        Some(path) => Ok(Some(load_yaml(&path)?)),
+        None => Ok(None),
     }
+}
 
-    // Try default paths
-    for name in &["betterprocs.yaml", "betterprocs.yml", "mprocs.yaml"] {
-        let path = Path::new(name);
-        if path.exists() {
-            return Ok(Some(load_yaml(path)?));
-        }
+/// The config file to load: the explicit path (which must then exist), or
+/// the first default file found.
+pub fn config_path(explicit_path: &Option<PathBuf>) -> Option<PathBuf> {
+    if let Some(path) = explicit_path {
+        return Some(path.clone());
     }
 
-    Ok(None)
+    ["betterprocs.yaml", "betterprocs.yml", "mprocs.yaml"]
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.exists())
+}
+
+/// Processes as far as they could be resolved, with the errors that
+/// stopped the rest.
+pub struct Resolved {
+    pub processes: Vec<ProcessConfig>,
+    pub errors: Vec<anyhow::Error>,
+    /// Processes with an unreadable env file or an unset `${VAR}`, whose
+    /// command, cwd and env are left as written
+    pub unresolved: Vec<String>,
 }
 
 impl YamlConfig {
-    pub fn into_process_configs(self) -> Vec<ProcessConfig> {
-        self.procs
+    /// Process configs with env files loaded and `${VAR}` references
+    /// resolved. Fails on unreadable env files and unset variables.
+    pub fn into_process_configs(self) -> Result<Vec<ProcessConfig>> {
+        let resolved = self.resolve();
+        match resolved.errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(resolved.processes),
+        }
+    }
+
+    /// Like `into_process_configs`, but going on past errors so that they
+    /// can all be reported at once.
+    pub fn resolve(self) -> Resolved {
+        let mut errors = Vec::new();
+        let mut unresolved = Vec::new();
+        let global_log = self.settings.log;
+        let global_env = match self.settings.env_file {
+            Some(ref files) => load_env_files(files.paths()).unwrap_or_else(|e| {
+                errors.push(e);
+                HashMap::new()
+            }),
+            None => HashMap::new(),
+        };
+        let clean_env = self.settings.clean_env;
+        let groups = group_membership(&self.groups, &self.procs).unwrap_or_else(|e| {
+            errors.push(e);
+            HashMap::new()
+        });
+        let processes = self
+            .procs
             .into_iter()
-            .map(|(name, entry)| match entry {
-                YamlProcEntry::Simple(cmd) => ProcessConfig {
+            .map(|(name, entry)| {
+                let failed = errors.len();
+                let mut config = match entry {
+                    YamlProcEntry::Simple(cmd) => ProcessConfig {
+                        log: LogOption::resolve(None, global_log.as_ref())
+                            .map(|l| l.into_config(&name)),
+                        name,
+                        command: cmd,
+                        autostart: true,
+                        file_env: global_env.clone(),
+                        clean_env,
+                        ..Default::default()
+                    },
+                    YamlProcEntry::Full(cfg) => full_process_config(
+                        name,
+                        *cfg,
+                        &global_log,
+                        &global_env,
+                        clean_env,
+                        &mut errors,
+                    ),
+                };
+                let raw = config.raw_command();
+                if let Err(e) = interpolate_config(&mut config, raw) {
+                    errors.push(e);
+                }
+                if errors.len() > failed {
+                    unresolved.push(config.name.clone());
+                }
+                config.group = groups.get(&config.name).cloned();
+                config
+            })
+            .collect();
+        Resolved {
+            processes,
+            errors,
+            unresolved,
+        }
+    }
+}
+
+/// The group of each process. A process can be in at most one group.
+fn group_membership(
+    groups: &IndexMap<String, Vec<String>>,
+    procs: &IndexMap<String, YamlProcEntry>,
+) -> Result<HashMap<String, String>> {
+    let mut membership: HashMap<String, String> = HashMap::new();
+    for (group, members) in groups {
+        for name in members {
+            if !procs.contains_key(name) {
+                let names: Vec<&str> = procs.keys().map(String::as_str).collect();
+                bail!(
+                    "Group \"{}\" has unknown process \"{}\"{}",
+                    group,
                     name,
-                    command: cmd,
-                    autostart: true,
-                    ..Default::default()
-                },
-                YamlProcEntry::Full(cfg) => ProcessConfig {
+                    suggestion(name, &names)
+                );
+            }
+            if let Some(other) = membership.insert(name.clone(), group.clone()) {
+                bail!(
+                    "Process \"{}\" is in both groups \"{}\" and \"{}\"",
                     name,
-                    command: cfg.shell.unwrap_or_default(),
-                    cmd: cfg.cmd,
-                    cwd: cfg.cwd,
-                    env: cfg.env,
-                    autostart: cfg.autostart,
-                    autorestart: cfg.autorestart,
-                    port: cfg.port,
-                },
-            })
-            .collect()
+                    other,
+                    group
+                );
+            }
+        }
+    }
+    Ok(membership)
+}
+
+fn full_process_config(
+    name: String,
+    cfg: YamlProcConfig,
+    global_log: &Option<LogOption>,
+    global_env: &HashMap<String, String>,
+    clean_env: bool,
+    errors: &mut Vec<anyhow::Error>,
+) -> ProcessConfig {
+    let log =
+        LogOption::resolve(cfg.log.as_ref(), global_log.as_ref()).map(|l| l.into_config(&name));
+    let mut file_env = global_env.clone();
+    if let Some(ref files) = cfg.env_file {
+        match load_env_files(files.paths()) {
+            Ok(env) => file_env.extend(env),
+            Err(e) => errors.push(e.context(format!("Invalid env_file for \"{}\"", name))),
+        }
+    }
+    ProcessConfig {
+        name,
+        command: cfg.shell.unwrap_or_default(),
+        cmd: cfg.cmd,
+        cwd: cfg.cwd,
+        env: cfg.env,
+        file_env,
+        clean_env: cfg.clean_env.unwrap_or(clean_env),
+        autostart: cfg.autostart,
+        restart: RestartConfig {
+            policy: cfg.restart.unwrap_or(if cfg.autorestart {
+                RestartPolicy::Always
+            } else {
+                RestartPolicy::Never
+            }),
+            delay: cfg.restart_delay,
+            max_delay: cfg.restart_max_delay,
+            max_restarts: cfg.max_restarts,
+            window: cfg.restart_window,
+        },
+        port: cfg.port,
+        depends_on: cfg.depends_on,
+        ready: cfg.ready,
+        healthcheck: cfg.healthcheck,
+        stop_signals: cfg.stop_signal,
+        stop_timeout: cfg.stop_timeout,
+        shutdown_priority: cfg.shutdown_priority,
+        log,
+        watch: cfg.watch.map(WatchOption::into_config),
+        group: None,
+        raw: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Result<Vec<ProcessConfig>> {
+        serde_yaml::from_str::<YamlConfig>(yaml).unwrap().into_process_configs()
+    }
+
+    #[test]
+    fn test_groups() {
+        let procs = parse(
+            "procs:\n  web: npm run dev\n  api: cargo run\n  db: postgres\n\
+             groups:\n  backend: [api, db]\n",
+        )
+        .unwrap();
+        let group = |name: &str| procs.iter().find(|p| p.name == name).unwrap().group.clone();
+        assert_eq!(group("api").as_deref(), Some("backend"));
+        assert_eq!(group("web"), None);
+
+        let unknown = parse("procs:\n  api: cargo run\ngroups:\n  backend: [api, worker]\n");
+        assert!(unknown.unwrap_err().to_string().contains("unknown process \"worker\""));
+        let twice = parse("procs:\n  api: cargo run\ngroups:\n  a: [api]\n  b: [api]\n");
+        assert!(twice.is_err());
     }
 }
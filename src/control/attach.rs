@@ -0,0 +1,102 @@
+use anyhow::{anyhow, bail, Context, Result};
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::protocol::{Request, Response};
+
+/// `betterprocs attach`: show the TUI of the background server for the
+/// current directory until detaching (d) or quitting (q).
+pub fn run() -> Result<()> {
+    let dir = std::env::current_dir().context("Failed to get current directory")?;
+    let path = super::socket_path(&dir);
+    if let Some(parent) = path.parent() {
+        super::check_socket_dir(parent)?;
+    }
+    let stream = UnixStream::connect(&path).map_err(|_| {
+        anyhow!(
+            "No betterprocs server running in {} (start one with --daemon)",
+            dir.display()
+        )
+    })?;
+
+    let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+    let mut request = serde_json::to_string(&Request::Attach { cols, rows })?;
+    request.push('\n');
+    (&stream).write_all(request.as_bytes())?;
+
+    // The reply is one JSON line; terminal output follows on the same stream
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut reply = String::new();
+    reader.read_line(&mut reply).context("Failed to read reply")?;
+    let response: Response = serde_json::from_str(&reply).context("Invalid reply")?;
+    if !response.ok {
+        bail!(response.error.unwrap_or_else(|| "Attach failed".to_string()));
+    }
+
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+    let result = relay(reader, stream);
+    disable_raw_mode()?;
+    execute!(
+        io::stdout(),
+        LeaveAlternateScreen,
+        DisableMouseCapture,
+        crossterm::cursor::Show
+    )?;
+    result?;
+
+    if still_running(&path) {
+        println!(
+            "Detached from betterprocs in {}; reattach with `betterprocs attach`",
+            dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// Copy server output to the terminal and input events to the server
+/// until the server closes the connection.
+fn relay(mut reader: BufReader<UnixStream>, mut stream: UnixStream) -> Result<()> {
+    let closed = Arc::new(AtomicBool::new(false));
+    let output_closed = closed.clone();
+    std::thread::spawn(move || {
+        let mut stdout = io::stdout();
+        let mut buf = [0u8; 8192];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if stdout.write_all(&buf[..n]).and_then(|_| stdout.flush()).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        output_closed.store(true, Ordering::SeqCst);
+    });
+
+    while !closed.load(Ordering::SeqCst) {
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        let mut line = serde_json::to_string(&event::read()?)?;
+        line.push('\n');
+        if stream.write_all(line.as_bytes()).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn still_running(path: &Path) -> bool {
+    UnixStream::connect(path).is_ok()
+}
@@ -0,0 +1,106 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+
+use super::protocol::{ProcessInfo, Request, Response};
+use crate::config::cli::CtlCommand;
+
+/// `betterprocs ctl ...`: send one command to the instance running in the
+/// current directory and print the result.
+pub fn run(command: CtlCommand) -> Result<()> {
+    let print_details = matches!(command, CtlCommand::Status { .. });
+    let request = match command {
+        CtlCommand::List => Request::List,
+        CtlCommand::Status { name } => Request::Status { name },
+        CtlCommand::Start { name } => Request::Start { name },
+        CtlCommand::Stop { name } => Request::Stop { name },
+        CtlCommand::Restart { name } => Request::Restart { name },
+        CtlCommand::Kill { name } => Request::Kill { name },
+        CtlCommand::SendInput {
+            name,
+            mut text,
+            no_enter,
+        } => {
+            if !no_enter {
+                text.push('\r');
+            }
+            Request::SendInput { name, data: text }
+        }
+        CtlCommand::Clear { name } => Request::Clear { name },
+    };
+
+    let response = send(&request)?;
+    if !response.ok {
+        bail!(response.error.unwrap_or_else(|| "Request failed".to_string()));
+    }
+
+    if print_details {
+        for info in &response.processes {
+            print_status(info);
+        }
+    } else {
+        print_table(&response.processes);
+    }
+    Ok(())
+}
+
+/// Send a request to the instance running in the current directory.
+pub fn send(request: &Request) -> Result<Response> {
+    let dir = std::env::current_dir().context("Failed to get current directory")?;
+    let path = super::socket_path(&dir);
+    if let Some(parent) = path.parent() {
+        super::check_socket_dir(parent)?;
+    }
+    let mut stream = UnixStream::connect(&path).map_err(|_| {
+        anyhow!(
+            "No betterprocs running in {} (no socket at {})",
+            dir.display(),
+            path.display()
+        )
+    })?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .context("Failed to read reply")?;
+    if reply.is_empty() {
+        bail!("betterprocs closed the connection");
+    }
+    serde_json::from_str(&reply).context("Invalid reply")
+}
+
+fn print_table(processes: &[ProcessInfo]) {
+    let width = processes
+        .iter()
+        .map(|p| p.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    for p in processes {
+        let pid = p.pid.map_or("-".to_string(), |pid| pid.to_string());
+        println!("{:<width$}  {:<11}  {:>7}  {}", p.name, p.status, pid, p.command);
+    }
+}
+
+fn print_status(p: &ProcessInfo) {
+    println!("name:     {}", p.name);
+    println!("status:   {}", p.status);
+    println!("command:  {}", p.command);
+    if let Some(pid) = p.pid {
+        println!("pid:      {}", pid);
+    }
+    if let Some(secs) = p.uptime_secs {
+        println!("uptime:   {}s", secs);
+    }
+    println!("restarts: {}", p.restarts);
+    if let (Some(exit), Some(code)) = (&p.exit, p.exit_code) {
+        println!("exit:     {} (code {})", exit, code);
+    }
+    if p.pid.is_some() {
+        println!("cpu:      {:.1}%", p.cpu_percent);
+        println!("memory:   {} KiB", p.rss_bytes / 1024);
+    }
+}
@@ -0,0 +1,100 @@
+pub mod attach;
+pub mod client;
+pub mod protocol;
+pub mod server;
+
+use anyhow::{bail, Context, Result};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+/// Control socket for the betterprocs instance started in `dir`. Sockets
+/// live in the user's runtime directory, keyed by a hash of the project
+/// path, since socket paths are limited to ~100 bytes.
+pub fn socket_path(dir: &Path) -> PathBuf {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let base = dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "root".to_string());
+    socket_dir().join(format!("{}-{:016x}.sock", base, fnv1a(dir.as_os_str().as_encoded_bytes())))
+}
+
+/// `$XDG_RUNTIME_DIR/betterprocs`, or a per-user directory under /tmp.
+pub fn socket_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("betterprocs"),
+        _ => std::env::temp_dir().join(format!("betterprocs-{}", nix::unistd::getuid())),
+    }
+}
+
+/// Create the socket directory if needed, and make sure it is private.
+pub fn ensure_socket_dir(dir: &Path) -> Result<()> {
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    check_socket_dir(dir)
+}
+
+/// The socket directory must belong to this user and be closed to everyone
+/// else. Under /tmp another user could create it first, then squat the
+/// socket or receive `ctl` commands. A directory that doesn't exist yet
+/// passes: there is nothing to connect to in it.
+pub fn check_socket_dir(dir: &Path) -> Result<()> {
+    let meta = match std::fs::symlink_metadata(dir) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    if !meta.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    if meta.uid() != nix::unistd::getuid().as_raw() {
+        bail!("{} belongs to another user", dir.display());
+    }
+    if meta.mode() & 0o777 != 0o700 {
+        bail!(
+            "{} must only be accessible by its owner (mode 700, not {:o})",
+            dir.display(),
+            meta.mode() & 0o777
+        );
+    }
+    Ok(())
+}
+
+/// Stable across builds, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_path_per_directory() {
+        let a = socket_path(Path::new("/srv/app"));
+        let b = socket_path(Path::new("/home/me/app"));
+        assert_ne!(a, b);
+        assert_eq!(a, socket_path(Path::new("/srv/app")));
+        let name = a.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("app-") && name.ends_with(".sock"));
+    }
+
+    #[test]
+    fn test_socket_dir_must_be_private() {
+        let dir = std::env::temp_dir().join(format!("betterprocs-sock-{}", std::process::id()));
+        ensure_socket_dir(&dir).unwrap();
+        assert_eq!(std::fs::metadata(&dir).unwrap().mode() & 0o777, 0o700);
+
+        let mut perms = std::fs::metadata(&dir).unwrap().permissions();
+        std::os::unix::fs::PermissionsExt::set_mode(&mut perms, 0o777);
+        std::fs::set_permissions(&dir, perms).unwrap();
+        assert!(ensure_socket_dir(&dir).is_err());
+        assert!(check_socket_dir(&dir.join("missing")).is_ok());
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
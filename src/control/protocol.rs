@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+
+use crate::process::handle::ProcessHandle;
+use crate::process::manager::ProcessManager;
+
+/// One command, sent as a single line of JSON, e.g.
+/// `{"cmd":"restart","name":"api"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+pub enum Request {
+    List,
+    Status { name: String },
+    Start { name: String },
+    Stop { name: String },
+    Restart { name: String },
+    Kill { name: String },
+    SendInput { name: String, data: String },
+    Clear { name: String },
+    /// Take over the connection to show the TUI of a background server.
+    /// After the reply, the client sends input events as JSON lines and
+    /// receives terminal output.
+    Attach { cols: u16, rows: u16 },
+}
+
+/// Reply to a request, also a single line of JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Every process for `list`, otherwise the one the command acted on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<ProcessInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub name: String,
+    pub status: String,
+    pub command: String,
+    pub pid: Option<u32>,
+    pub uptime_secs: Option<u64>,
+    pub restarts: u32,
+    /// How the last run ended, e.g. "exit 1" or "SIGSEGV"
+    pub exit: Option<String>,
+    pub exit_code: Option<i32>,
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+}
+
+impl ProcessInfo {
+    pub fn from_handle(handle: &ProcessHandle) -> Self {
+        let exit = handle.status.exit();
+        Self {
+            name: handle.config.name.clone(),
+            status: handle.status.label().to_string(),
+            command: handle.config.command.clone(),
+            pid: handle.status.pid(),
+            uptime_secs: handle.uptime().map(|d| d.as_secs()),
+            restarts: handle.restart_count,
+            exit: exit.map(|e| e.describe()),
+            exit_code: exit.map(|e| e.exit_code()),
+            cpu_percent: handle.usage.cpu_percent,
+            rss_bytes: handle.usage.rss_bytes,
+        }
+    }
+}
+
+impl Response {
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(msg.into()),
+            processes: Vec::new(),
+        }
+    }
+}
+
+/// Carry out a request against the running processes.
+pub fn handle_request(pm: &mut ProcessManager, request: Request) -> Response {
+    let name = match request {
+        Request::List => {
+            return Response {
+                ok: true,
+                error: None,
+                processes: pm.processes.iter().map(ProcessInfo::from_handle).collect(),
+            }
+        }
+        Request::Status { ref name }
+        | Request::Start { ref name }
+        | Request::Stop { ref name }
+        | Request::Restart { ref name }
+        | Request::Kill { ref name }
+        | Request::SendInput { ref name, .. }
+        | Request::Clear { ref name } => name.clone(),
+        Request::Attach { .. } => {
+            return Response::error(
+                "This betterprocs isn't running as a server; start it with --daemon to attach",
+            )
+        }
+    };
+    let Some(idx) = pm.index_of(&name) else {
+        return Response::error(format!("No process named \"{}\"", name));
+    };
+    if pm.is_shutting_down() && !matches!(request, Request::Status { .. }) {
+        return Response::error("betterprocs is shutting down");
+    }
+
+    let result = match request {
+        Request::Start { .. } => pm.start(idx),
+        Request::Stop { .. } => pm.stop(idx),
+        Request::Restart { .. } => pm.restart(idx),
+        Request::Kill { .. } => pm.force_kill(idx),
+        Request::SendInput { data, .. } => {
+            if pm.processes[idx].status.is_running() {
+                pm.processes[idx].write_input(data.as_bytes())
+            } else {
+                return Response::error(format!("\"{}\" is not running", name));
+            }
+        }
+        Request::Clear { .. } => {
+            pm.processes[idx].screen.clear();
+            Ok(())
+        }
+        Request::List | Request::Status { .. } | Request::Attach { .. } => Ok(()),
+    };
+
+    match result {
+        Ok(()) => Response {
+            ok: true,
+            error: None,
+            processes: vec![ProcessInfo::from_handle(&pm.processes[idx])],
+        },
+        Err(e) => Response::error(format!("{:#}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::types::ProcessConfig;
+
+    fn manager() -> ProcessManager {
+        let mut pm = ProcessManager::new();
+        for name in ["api", "web"] {
+            pm.add_process(
+                ProcessConfig {
+                    name: name.to_string(),
+                    command: "true".to_string(),
+                    autostart: false,
+                    ..Default::default()
+                },
+                24,
+                80,
+            );
+        }
+        pm
+    }
+
+    #[test]
+    fn test_parse_requests() {
+        let req: Request = serde_json::from_str(r#"{"cmd":"restart","name":"api"}"#).unwrap();
+        assert_eq!(req, Request::Restart { name: "api".to_string() });
+        let req: Request =
+            serde_json::from_str(r#"{"cmd":"send-input","name":"api","data":"y\r"}"#).unwrap();
+        assert_eq!(
+            req,
+            Request::SendInput {
+                name: "api".to_string(),
+                data: "y\r".to_string()
+            }
+        );
+        assert!(serde_json::from_str::<Request>(r#"{"cmd":"explode"}"#).is_err());
+    }
+
+    #[test]
+    fn test_list_and_status() {
+        let mut pm = manager();
+        let resp = handle_request(&mut pm, Request::List);
+        assert!(resp.ok);
+        assert_eq!(resp.processes.len(), 2);
+
+        let resp = handle_request(&mut pm, Request::Status { name: "web".to_string() });
+        assert_eq!(resp.processes[0].name, "web");
+        assert_eq!(resp.processes[0].status, "NOT STARTED");
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut pm = manager();
+        let resp = handle_request(&mut pm, Request::Stop { name: "db".to_string() });
+        assert!(!resp.ok);
+        assert_eq!(resp.error.as_deref(), Some("No process named \"db\""));
+
+        let resp = handle_request(
+            &mut pm,
+            Request::SendInput {
+                name: "api".to_string(),
+                data: "x".to_string(),
+            },
+        );
+        assert!(!resp.ok);
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::{bail, Context, Result};
+use crossterm::event::Event;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use super::protocol::{handle_request, Request, Response};
+use crate::process::manager::ProcessManager;
+
+type Pending = (Request, oneshot::Sender<Response>);
+
+/// A client attached to a background server's TUI.
+pub struct Attachment {
+    pub cols: u16,
+    pub rows: u16,
+    /// Input events from the client; closed when it disconnects
+    pub events: mpsc::UnboundedReceiver<Event>,
+    /// Terminal output for the client; dropping it closes the connection
+    pub output: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+/// Listens on the control socket. Connections are served on background
+/// tasks, but requests are queued and carried out by `poll` on the main
+/// loop, which owns the processes.
+pub struct ControlServer {
+    path: PathBuf,
+    requests: mpsc::UnboundedReceiver<Pending>,
+    /// Only set for background servers, which accept `attach`
+    attachments: Option<mpsc::UnboundedReceiver<Attachment>>,
+    accept_task: JoinHandle<()>,
+}
+
+impl ControlServer {
+    /// Bind the socket for the project in `dir`. Fails if another instance
+    /// is already listening there; a stale socket file is replaced.
+    pub fn bind(dir: &Path, allow_attach: bool) -> Result<Self> {
+        let path = super::socket_path(dir);
+        if let Some(parent) = path.parent() {
+            super::ensure_socket_dir(parent)?;
+        }
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                bail!("betterprocs is already running in {}", dir.display());
+            }
+            std::fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+        }
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+
+        let (tx, requests) = mpsc::unbounded_channel();
+        let (attach_tx, attachments) = if allow_attach {
+            let (tx, rx) = mpsc::unbounded_channel();
+            (Some(tx), Some(rx))
+        } else {
+            (None, None)
+        };
+        let accept_task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, tx.clone(), attach_tx.clone()));
+            }
+        });
+
+        Ok(Self {
+            path,
+            requests,
+            attachments,
+            accept_task,
+        })
+    }
+
+    /// Bind in the current directory. Callers carry on without a control
+    /// socket when this fails, after telling the user.
+    pub fn start() -> Result<Self> {
+        let dir = std::env::current_dir().unwrap_or_default();
+        Self::bind(&dir, false)
+    }
+
+    /// Carry out queued requests.
+    pub fn poll(&mut self, pm: &mut ProcessManager) {
+        while let Ok((request, reply)) = self.requests.try_recv() {
+            let _ = reply.send(handle_request(pm, request));
+        }
+    }
+
+    /// The newest client that asked to attach, if any.
+    pub fn take_attachment(&mut self) -> Option<Attachment> {
+        let attachments = self.attachments.as_mut()?;
+        let mut newest = None;
+        while let Ok(attachment) = attachments.try_recv() {
+            newest = Some(attachment);
+        }
+        newest
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Read newline-delimited requests and write one response line for each.
+async fn serve_connection(
+    stream: UnixStream,
+    tx: mpsc::UnboundedSender<Pending>,
+    attach_tx: Option<mpsc::UnboundedSender<Attachment>>,
+) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Attach { cols, rows }) if attach_tx.is_some() => {
+                if write_response(&mut writer, &Response { ok: true, ..Default::default() })
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                let (event_tx, events) = mpsc::unbounded_channel();
+                let (output, output_rx) = mpsc::unbounded_channel();
+                let attachment = Attachment {
+                    cols,
+                    rows,
+                    events,
+                    output,
+                };
+                if let Some(attach_tx) = attach_tx {
+                    if attach_tx.send(attachment).is_ok() {
+                        serve_attached(lines, writer, event_tx, output_rx).await;
+                    }
+                }
+                return;
+            }
+            Ok(request) => {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                if tx.send((request, reply_tx)).is_err() {
+                    return;
+                }
+                match reply_rx.await {
+                    Ok(response) => response,
+                    Err(_) => return,
+                }
+            }
+            Err(e) => Response::error(format!("Invalid request: {}", e)),
+        };
+
+        if write_response(&mut writer, &response).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn write_response(writer: &mut OwnedWriteHalf, response: &Response) -> Result<()> {
+    let mut json = serde_json::to_string(response)?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await?;
+    Ok(())
+}
+
+/// Relay an attached client: its input events go to the server loop, the
+/// server's terminal output goes back. Ends when either side goes away.
+async fn serve_attached(
+    mut lines: Lines<BufReader<OwnedReadHalf>>,
+    mut writer: OwnedWriteHalf,
+    events: mpsc::UnboundedSender<Event>,
+    mut output: mpsc::UnboundedReceiver<Vec<u8>>,
+) {
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Ok(Some(line)) = line else {
+                    return;
+                };
+                if let Ok(event) = serde_json::from_str::<Event>(&line) {
+                    if events.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+            data = output.recv() => {
+                let Some(data) = data else {
+                    return;
+                };
+                if writer.write_all(&data).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
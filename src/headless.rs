@@ -0,0 +1,146 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+
+use crate::config::merged::AppConfig;
+use crate::control::server::ControlServer;
+use crate::process::manager::ProcessManager;
+use crate::terminal::merged::MergedLog;
+
+/// ANSI foreground colors for the name prefixes (same order as the TUI palette)
+const PALETTE: [&str; 8] = ["36", "32", "33", "35", "34", "91", "92", "94"];
+
+/// Run without the TUI: print every line as `[name] text` to stdout until
+/// interrupted, or until everything has stopped with `--auto-exit`.
+/// SIGINT / SIGTERM stop all process groups gracefully; a second one kills them.
+/// Prints a summary at the end and returns the exit code for betterprocs.
+pub async fn run(mut config: AppConfig) -> Result<i32> {
+    let (cols, rows) = crossterm::terminal::size().unwrap_or((120, 40));
+    let mut pm = ProcessManager::new();
+    for proc_config in config.processes.drain(..) {
+        pm.add_process(proc_config, rows, cols);
+    }
+
+    let mut control = match ControlServer::start() {
+        Ok(server) => Some(server),
+        Err(e) => {
+            eprintln!("Warning: Control socket disabled: {:#}", e);
+            None
+        }
+    };
+    let mut printer = Printer::new(&pm);
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+
+    loop {
+        pm.drain_output();
+        pm.check_stopping();
+        pm.check_ready();
+        pm.check_health();
+        pm.check_autorestart();
+        pm.check_watches();
+        pm.start_pending();
+        pm.sample_usage();
+        if let Some(ref mut control) = control {
+            control.poll(&mut pm);
+        }
+        printer.print_new(&pm)?;
+
+        if pm.is_shutting_down() {
+            pm.advance_shutdown();
+            if pm.all_stopped() {
+                break;
+            }
+        } else if config.exit.should_stop_all(&pm) {
+            pm.begin_shutdown();
+        } else if config.auto_exit && pm.process_count() > 0 && pm.all_stopped() {
+            break;
+        }
+
+        let interrupted = tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(50)) => false,
+            _ = sigint.recv() => true,
+            _ = sigterm.recv() => true,
+        };
+        if interrupted {
+            if pm.is_shutting_down() {
+                eprintln!("Force killing all processes");
+                pm.force_kill_all();
+            } else {
+                eprintln!("Stopping all processes (send the signal again to force kill)");
+                pm.begin_shutdown();
+            }
+        }
+    }
+
+    pm.drain_output();
+    printer.print_new(&pm)?;
+    config.exit.print_summary(&pm)?;
+    Ok(config.exit.exit_code(&pm))
+}
+
+struct Printer {
+    /// Merged log lines already printed
+    seen: u64,
+    name_width: usize,
+    color: bool,
+    /// Exit time last reported per process
+    reported_exits: HashMap<String, SystemTime>,
+}
+
+impl Printer {
+    fn new(pm: &ProcessManager) -> Self {
+        Self {
+            seen: 0,
+            name_width: pm
+                .processes
+                .iter()
+                .map(|h| h.config.name.chars().count())
+                .max()
+                .unwrap_or(0),
+            color: io::stdout().is_terminal(),
+            reported_exits: HashMap::new(),
+        }
+    }
+
+    fn prefix(&self, name: &str) -> String {
+        let padding = " ".repeat(self.name_width.saturating_sub(name.chars().count()));
+        if self.color {
+            let color = PALETTE[MergedLog::color_index(name, PALETTE.len())];
+            format!("\x1b[{}m[{}]\x1b[0m{} ", color, name, padding)
+        } else {
+            format!("[{}]{} ", name, padding)
+        }
+    }
+
+    fn print_new(&mut self, pm: &ProcessManager) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+
+        for line in pm.merged.since(self.seen) {
+            writeln!(out, "{}{}", self.prefix(&line.process), line.text)?;
+        }
+        self.seen = pm.merged.pushed();
+
+        for handle in &pm.processes {
+            let Some(exit) = handle.status.exit() else {
+                continue;
+            };
+            let name = &handle.config.name;
+            if self.reported_exits.get(name) == Some(&exit.at) {
+                continue;
+            }
+            self.reported_exits.insert(name.clone(), exit.at);
+            writeln!(
+                out,
+                "{}{}: {}",
+                self.prefix(name),
+                handle.status.label(),
+                exit.describe()
+            )?;
+        }
+
+        out.flush()
+    }
+}
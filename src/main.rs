@@ -1,36 +1,71 @@
 mod app;
 mod config;
+mod control;
+mod headless;
 mod port;
 mod process;
+mod server;
 mod system;
 mod terminal;
 mod tui;
 
 use anyhow::Result;
-use app::App;
+use app::{App, Toast};
 use clap::Parser;
-use config::cli::Cli;
+use config::cli::{Cli, Command};
 use config::merged::load_config;
+use control::server::ControlServer;
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::io;
+use std::io::{self, IsTerminal};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    match cli.command {
+        Some(Command::Ctl { command }) => return control::client::run(command),
+        Some(Command::Attach) => return control::attach::run(),
+        Some(Command::Schema) => return config::schema::run(),
+        Some(Command::Check { ref config }) => {
+            let code = config::check::run(config)?;
+            std::process::exit(code);
+        }
+        None => {}
+    }
+
     // Load config before entering TUI (errors print to normal terminal)
     let app_config = load_config(&cli)?;
 
-    // Port conflict detection (runs before TUI)
+    // A background server has no terminal to ask about conflicts on;
+    // --daemon already asked before starting it
+    if cli.serve {
+        return server::run(app_config).await;
+    }
+
+    // Port conflict detection (runs before TUI). Without a terminal to
+    // answer on, e.g. in CI, conflicts are only reported.
     let conflicts = port::detector::detect_conflicts(&app_config.processes);
-    if !port::detector::handle_conflicts(&conflicts)? {
+    if cli.headless || !io::stdin().is_terminal() {
+        port::detector::warn_conflicts(&conflicts);
+    } else if !port::detector::handle_conflicts(&conflicts)? {
         return Ok(());
     }
 
+    if cli.daemon {
+        let dir = std::env::current_dir()?;
+        server::spawn_daemon(&dir)?;
+        return control::attach::run();
+    }
+
+    if cli.headless {
+        let code = headless::run(app_config).await?;
+        std::process::exit(code);
+    }
+
     // Install panic hook that restores terminal before printing panic info
     let original_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
@@ -39,6 +74,9 @@ async fn main() -> Result<()> {
         original_hook(panic_info);
     }));
 
+    // Bind before the TUI takes over the screen; a failure is shown in it
+    let control = ControlServer::start();
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -49,6 +87,12 @@ async fn main() -> Result<()> {
 
     // Run app
     let mut app = App::new(app_config);
+    match control {
+        Ok(server) => app.control = Some(server),
+        Err(e) => {
+            app.ui_state.toast = Some(Toast::error(format!("Control socket disabled: {:#}", e)));
+        }
+    }
     let result = app.run(&mut terminal).await;
 
     // Restore terminal
@@ -59,6 +103,14 @@ async fn main() -> Result<()> {
         DisableMouseCapture
     )?;
     terminal.show_cursor()?;
+    result?;
 
-    result
+    if app.exit.enabled() {
+        app.exit.print_summary(&app.process_manager)?;
+        let code = app.exit.exit_code(&app.process_manager);
+        // Everything has stopped already; exiting skips no cleanup
+        drop(app);
+        std::process::exit(code);
+    }
+    Ok(())
 }
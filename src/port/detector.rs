@@ -71,12 +71,7 @@ pub fn handle_conflicts(conflicts: &[PortConflict]) -> Result<bool> {
     }
 
     eprintln!("\nPort conflicts detected:");
-    for c in conflicts {
-        eprintln!(
-            "  Port {}: used by {} (PID {}) — needed by \"{}\"",
-            c.port, c.process_name, c.pid, c.our_process
-        );
-    }
+    print_conflicts(conflicts);
     eprintln!();
     eprint!("[K]ill conflicting processes  [I]gnore  [Q]uit: ");
     io::stderr().flush()?;
@@ -108,3 +103,22 @@ pub fn handle_conflicts(conflicts: &[PortConflict]) -> Result<bool> {
         _ => Ok(true),
     }
 }
+
+/// Report port conflicts without asking, for when there is no one to ask
+/// (`--headless`, or stdin is not a terminal). Startup continues.
+pub fn warn_conflicts(conflicts: &[PortConflict]) {
+    if conflicts.is_empty() {
+        return;
+    }
+    eprintln!("Warning: port conflicts detected:");
+    print_conflicts(conflicts);
+}
+
+fn print_conflicts(conflicts: &[PortConflict]) {
+    for c in conflicts {
+        eprintln!(
+            "  Port {}: used by {} (PID {}) — needed by \"{}\"",
+            c.port, c.process_name, c.pid, c.our_process
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+use super::types::ProcessConfig;
+use crate::config::errors::suggestion;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Unvisited,
+    Visiting,
+    Done,
+}
+
+/// Order processes so every process comes after the processes it depends on.
+/// Processes without dependencies keep their original (file) order.
+/// Returns the config indices in start order, or an error naming the cycle.
+pub fn topological_order(configs: &[ProcessConfig]) -> Result<Vec<usize>> {
+    let index: HashMap<&str, usize> = configs
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.name.as_str(), i))
+        .collect();
+
+    for config in configs {
+        for dep in &config.depends_on {
+            if !index.contains_key(dep.as_str()) {
+                let names: Vec<&str> = index.keys().copied().collect();
+                bail!(
+                    "Process \"{}\" depends on unknown process \"{}\"{}",
+                    config.name,
+                    dep,
+                    suggestion(dep, &names)
+                );
+            }
+        }
+    }
+
+    let mut marks = vec![Mark::Unvisited; configs.len()];
+    let mut order = Vec::with_capacity(configs.len());
+    let mut path = Vec::new();
+
+    for i in 0..configs.len() {
+        visit(i, configs, &index, &mut marks, &mut path, &mut order)?;
+    }
+
+    Ok(order)
+}
+
+fn visit(
+    i: usize,
+    configs: &[ProcessConfig],
+    index: &HashMap<&str, usize>,
+    marks: &mut [Mark],
+    path: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<()> {
+    match marks[i] {
+        Mark::Done => return Ok(()),
+        Mark::Visiting => {
+            // `path` holds the current DFS stack; the cycle starts where `i` was entered
+            let start = path.iter().position(|&p| p == i).unwrap_or(0);
+            let cycle: Vec<&str> = path[start..]
+                .iter()
+                .chain(std::iter::once(&i))
+                .map(|&p| configs[p].name.as_str())
+                .collect();
+            bail!("Dependency cycle detected: {}", cycle.join(" -> "));
+        }
+        Mark::Unvisited => {}
+    }
+
+    marks[i] = Mark::Visiting;
+    path.push(i);
+    for dep in &configs[i].depends_on {
+        visit(index[dep.as_str()], configs, index, marks, path, order)?;
+    }
+    path.pop();
+    marks[i] = Mark::Done;
+    order.push(i);
+
+    Ok(())
+}
+
+/// Depth of each process in the dependency graph: 0 without dependencies,
+/// otherwise one more than its deepest dependency. Unknown names and cycles
+/// (possible for processes added at runtime) are treated as depth 0.
+pub fn dependency_depths<'a>(
+    configs: impl IntoIterator<Item = &'a ProcessConfig>,
+) -> HashMap<String, usize> {
+    let deps: HashMap<&str, &[String]> = configs
+        .into_iter()
+        .map(|c| (c.name.as_str(), c.depends_on.as_slice()))
+        .collect();
+
+    fn depth<'a>(
+        name: &'a str,
+        deps: &HashMap<&'a str, &'a [String]>,
+        memo: &mut HashMap<String, usize>,
+        stack: &mut Vec<&'a str>,
+    ) -> usize {
+        if let Some(&d) = memo.get(name) {
+            return d;
+        }
+        if stack.contains(&name) {
+            return 0;
+        }
+        stack.push(name);
+        let d = deps
+            .get(name)
+            .map(|ds| {
+                ds.iter()
+                    .filter(|d| deps.contains_key(d.as_str()))
+                    .map(|d| depth(d, deps, memo, stack) + 1)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .unwrap_or(0);
+        stack.pop();
+        memo.insert(name.to_string(), d);
+        d
+    }
+
+    let mut memo = HashMap::new();
+    for name in deps.keys() {
+        depth(name, &deps, &mut memo, &mut Vec::new());
+    }
+    memo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependencies_start_first() {
+        let configs = vec![
+            ProcessConfig::test("frontend", "true").depending_on(&["backend"]),
+            ProcessConfig::test("backend", "true").depending_on(&["db"]),
+            ProcessConfig::test("db", "true"),
+        ];
+        assert_eq!(topological_order(&configs).unwrap(), vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_independent_keep_file_order() {
+        let configs = vec![
+            ProcessConfig::test("a", "true"),
+            ProcessConfig::test("b", "true"),
+            ProcessConfig::test("c", "true").depending_on(&["a"]),
+        ];
+        assert_eq!(topological_order(&configs).unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_cycle_is_reported() {
+        let configs = vec![
+            ProcessConfig::test("a", "true").depending_on(&["b"]),
+            ProcessConfig::test("b", "true").depending_on(&["c"]),
+            ProcessConfig::test("c", "true").depending_on(&["a"]),
+        ];
+        let err = topological_order(&configs).unwrap_err().to_string();
+        assert_eq!(err, "Dependency cycle detected: a -> b -> c -> a");
+    }
+
+    #[test]
+    fn test_dependency_depths() {
+        let configs = vec![
+            ProcessConfig::test("frontend", "true").depending_on(&["backend"]),
+            ProcessConfig::test("backend", "true").depending_on(&["db", "cache"]),
+            ProcessConfig::test("db", "true"),
+            ProcessConfig::test("cache", "true"),
+            ProcessConfig::test("docs", "true"),
+        ];
+        let depths = dependency_depths(&configs);
+        assert_eq!(depths["frontend"], 2);
+        assert_eq!(depths["backend"], 1);
+        assert_eq!(depths["db"], 0);
+        assert_eq!(depths["docs"], 0);
+    }
+
+    #[test]
+    fn test_unknown_dependency() {
+        let configs = vec![ProcessConfig::test("api", "true").depending_on(&["postgres"])];
+        let err = topological_order(&configs).unwrap_err().to_string();
+        assert!(err.contains("unknown process \"postgres\""));
+    }
+}
@@ -1,10 +1,22 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use regex::Regex;
+use std::collections::VecDeque;
 use std::io::Read;
+use std::path::PathBuf;
 use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
 
+use nix::sys::signal::Signal;
+
+use super::log::LogWriter;
+use super::probe::{ProbeEnv, ProbeRunner};
 use super::signal;
-use super::types::{ProcessConfig, ProcessStatus};
+use super::types::{ExitInfo, ProcessConfig, ProcessStatus, StopCause};
+use super::usage::ResourceUsage;
+use super::watch::FileWatcher;
+use crate::config::env::kept_env;
+use crate::terminal::lines::LineBuffer;
 use crate::terminal::screen::TerminalScreen;
 
 pub struct ProcessHandle {
@@ -15,11 +27,55 @@ pub struct ProcessHandle {
     master_pty: Option<Box<dyn portable_pty::MasterPty + Send>>,
     output_rx: Option<std::sync::mpsc::Receiver<Vec<u8>>>,
     reader_thread: Option<std::thread::JoinHandle<()>>,
+    /// Readiness probe, active while the process is `Starting`
+    ready_probe: Option<ProbeRunner>,
+    /// Log readiness pattern, matched against plain-text output lines
+    ready_regex: Option<Regex>,
+    lines: LineBuffer,
+    /// Complete lines drained since the last `take_lines`
+    new_lines: Vec<String>,
+    /// Health check, active once the process is up
+    health_probe: Option<ProbeRunner>,
+    /// Consecutive failed health checks
+    pub health_failures: u32,
+    /// When the current (or last) run was spawned
+    pub started_at: Option<SystemTime>,
+    /// When the process last exited or finished stopping
+    pub last_exit_at: Option<SystemTime>,
+    /// Times the process was started again after its first start, for any reason
+    pub restart_count: u32,
+    /// CPU and memory of the process group, refreshed by the manager
+    pub usage: ResourceUsage,
+    /// When the pending automatic restart fires
+    pub next_restart: Option<Instant>,
+    /// Gave up restarting after `max_restarts` within the restart window
+    pub crash_looping: bool,
+    /// The last stop came from the user, not from the process exiting
+    pub stopped_by_user: bool,
+    restart_history: VecDeque<Instant>,
+    /// Escalation state while `Stopping`
+    stop_plan: Option<StopPlan>,
+    /// Exit status of the main process, once reaped while `Stopping`
+    stop_exit: Option<portable_pty::ExitStatus>,
+    /// Spawn again as soon as the current stop completes
+    restart_pending: bool,
+    /// Last "waiting for" notice, so it is printed once per cause
+    waiting_notice: Option<String>,
+    /// Watches the files in `config.watch`
+    file_watcher: Option<FileWatcher>,
+}
+
+/// Signals still to send while stopping, and when to send the next one.
+struct StopPlan {
+    remaining: VecDeque<Signal>,
+    /// None once SIGKILL has been sent: just wait for the group to go away
+    deadline: Option<Instant>,
 }
 
 impl ProcessHandle {
+    /// Create a handle without spawning. `ProcessManager` decides when to
+    /// start it, since dependencies may need to come up first.
     pub fn new(config: ProcessConfig, rows: u16, cols: u16) -> Self {
-        let autostart = config.autostart;
         let mut handle = Self {
             config,
             status: ProcessStatus::NotStarted,
@@ -28,18 +84,59 @@ impl ProcessHandle {
             master_pty: None,
             output_rx: None,
             reader_thread: None,
+            ready_probe: None,
+            ready_regex: None,
+            lines: LineBuffer::new(),
+            new_lines: Vec::new(),
+            health_probe: None,
+            health_failures: 0,
+            started_at: None,
+            last_exit_at: None,
+            restart_count: 0,
+            usage: ResourceUsage::default(),
+            next_restart: None,
+            crash_looping: false,
+            stopped_by_user: false,
+            restart_history: VecDeque::new(),
+            stop_plan: None,
+            stop_exit: None,
+            restart_pending: false,
+            waiting_notice: None,
+            file_watcher: None,
         };
+        handle.start_watching();
+        handle
+    }
 
-        if autostart {
-            let _ = handle.spawn();
+    /// Replace the config (edited or reloaded). Takes effect on the next
+    /// start, except for `watch`, which applies right away.
+    pub fn set_config(&mut self, config: ProcessConfig) {
+        let rewatch = config.watch != self.config.watch || config.cwd != self.config.cwd;
+        self.config = config;
+        if rewatch {
+            self.start_watching();
         }
+    }
 
-        handle
+    fn start_watching(&mut self) {
+        self.file_watcher = None;
+        let Some(ref watch) = self.config.watch else {
+            return;
+        };
+        match FileWatcher::new(watch, self.config.cwd.as_deref()) {
+            Ok(watcher) => self.file_watcher = Some(watcher),
+            Err(e) => self.print_notice(&format!("Not watching files: {:#}", e)),
+        }
+    }
+
+    /// A watched file that changed, once changes have settled.
+    pub fn check_watch(&mut self) -> Option<PathBuf> {
+        self.file_watcher.as_mut()?.poll()
     }
 
     pub fn spawn(&mut self) -> Result<()> {
         if self.status.is_running() {
-            self.stop(true)?;
+            bail!("\"{}\" is still running", self.config.name);
         }
 
         let pty_system = native_pty_system();
@@ -68,7 +165,13 @@ impl ProcessHandle {
             cmd.cwd(cwd);
         }
 
-        for (key, value) in &self.config.env {
+        if self.config.clean_env {
+            cmd.env_clear();
+            for (key, value) in kept_env() {
+                cmd.env(key, value);
+            }
+        }
+        for (key, value) in self.config.file_env.iter().chain(&self.config.env) {
             cmd.env(key, value);
         }
 
@@ -86,60 +189,163 @@ impl ProcessHandle {
             .try_clone_reader()
             .context("Failed to clone PTY reader")?;
 
+        let log = match self.config.log.clone().map(LogWriter::open) {
+            Some(Ok(writer)) => Some(writer),
+            Some(Err(e)) => {
+                self.print_notice(&format!("Not logging to file: {:#}", e));
+                None
+            }
+            None => None,
+        };
+
         let (tx, rx) = std::sync::mpsc::channel::<Vec<u8>>();
         let reader_thread = std::thread::spawn(move || {
-            read_pty_output(reader, tx);
+            read_pty_output(reader, tx, log);
         });
 
         self.child = Some(child);
         self.master_pty = Some(pty_pair.master);
         self.output_rx = Some(rx);
         self.reader_thread = Some(reader_thread);
-        self.status = ProcessStatus::Running { pid };
+        self.stopped_by_user = false;
+        self.waiting_notice = None;
+        if self.started_at.is_some() {
+            self.restart_count += 1;
+        }
+        self.started_at = Some(SystemTime::now());
+        self.lines = LineBuffer::new();
+        self.status = match self.config.ready {
+            Some(ref ready) => {
+                self.ready_regex = ready.log_regex();
+                self.ready_probe = Some(ProbeRunner::new(ready.clone(), self.probe_env()));
+                ProcessStatus::Starting { pid }
+            }
+            None => ProcessStatus::Running { pid },
+        };
+        if let Some(ref healthcheck) = self.config.healthcheck {
+            // The first check waits one interval so a fresh process isn't judged mid-boot
+            self.health_probe = Some(
+                ProbeRunner::new(healthcheck.probe_config(), self.probe_env())
+                    .delay_first_attempt(healthcheck.interval),
+            );
+            self.health_failures = 0;
+        }
         self.screen.scroll_to_bottom();
 
         Ok(())
     }
 
+    /// Begin stopping the process group without blocking. A graceful stop
+    /// sends the configured stop signals in turn, waiting `stop_timeout`
+    /// after each, then SIGKILL. The status stays `Stopping` until the whole
+    /// group is gone; `check_stopping` drives the escalation.
     pub fn stop(&mut self, graceful: bool) -> Result<()> {
-        if let ProcessStatus::Running { pid } = self.status {
-            if graceful {
-                let _ = signal::terminate_process_group(pid);
-                std::thread::sleep(std::time::Duration::from_millis(100));
-                if signal::is_process_alive(pid) {
-                    let _ = signal::force_kill_process_group(pid);
-                }
-            } else {
-                let _ = signal::force_kill_process_group(pid);
-            }
+        if matches!(self.status, ProcessStatus::Waiting) {
+            self.status = ProcessStatus::NotStarted;
+            self.waiting_notice = None;
+        }
 
-            if let Some(ref mut child) = self.child {
-                match child.wait() {
-                    Ok(exit_status) => {
-                        let code = exit_status
-                            .exit_code()
-                            .try_into()
-                            .unwrap_or(-1);
-                        self.status = ProcessStatus::Stopped { exit_code: code };
-                    }
-                    Err(_) => {
-                        self.status = ProcessStatus::Crashed {};
-                    }
+        let pid = match self.status {
+            ProcessStatus::Stopping { pid } => {
+                // Already stopping: only a force kill changes anything
+                if !graceful {
+                    self.send_stop_signal(pid, Signal::SIGKILL);
                 }
+                return Ok(());
             }
+            _ => match self.status.pid() {
+                Some(pid) => pid,
+                None => return Ok(()),
+            },
+        };
 
-            self.child = None;
+        let mut signals: VecDeque<Signal> = if graceful {
+            self.config.stop_signals.iter().copied().collect()
+        } else {
+            VecDeque::new()
+        };
+        let first = signals.pop_front().unwrap_or(Signal::SIGKILL);
+
+        self.clear_probes();
+        self.stop_exit = None;
+        self.status = ProcessStatus::Stopping { pid };
+        self.stop_plan = Some(StopPlan {
+            remaining: signals,
+            deadline: None,
+        });
+        self.send_stop_signal(pid, first);
+
+        Ok(())
+    }
+
+    /// Advance a pending stop: escalate when the timeout passes, and finish
+    /// once the process has exited and its group is empty.
+    pub fn check_stopping(&mut self) {
+        let ProcessStatus::Stopping { pid } = self.status else {
+            return;
+        };
+
+        if self.child.is_none() && !signal::is_group_alive(pid) {
+            let cause = if self.stopped_by_user {
+                StopCause::User
+            } else {
+                StopCause::Manager
+            };
+            let exit = match self.stop_exit.take() {
+                Some(ref status) => ExitInfo::from_exit_status(status, cause),
+                None => ExitInfo::unknown(cause),
+            };
+            self.last_exit_at = Some(exit.at);
+            self.status = ProcessStatus::Stopped { exit };
+            self.stop_plan = None;
             self.master_pty = None;
-            self.output_rx = None;
             self.reader_thread = None;
+            if self.restart_pending {
+                self.restart_pending = false;
+                if let Err(e) = self.spawn() {
+                    self.print_notice(&format!("Failed to restart: {}", e));
+                    self.status = ProcessStatus::Crashed {
+                        exit: ExitInfo::unknown(StopCause::Exited),
+                    };
+                }
+            }
+            return;
         }
 
-        Ok(())
+        let next = match self.stop_plan {
+            Some(StopPlan {
+                deadline: Some(deadline),
+                ref mut remaining,
+            }) if Instant::now() >= deadline => remaining.pop_front().unwrap_or(Signal::SIGKILL),
+            _ => return,
+        };
+        self.print_notice(&format!(
+            "Still running after {}s, sending SIG{}",
+            self.config.stop_timeout.as_secs_f32(),
+            signal::signal_name(next)
+        ));
+        self.send_stop_signal(pid, next);
     }
 
+    fn send_stop_signal(&mut self, pid: u32, sig: Signal) {
+        let _ = signal::signal_process_group(pid, sig);
+        if let Some(ref mut plan) = self.stop_plan {
+            plan.deadline = if sig == Signal::SIGKILL {
+                None
+            } else {
+                Some(Instant::now() + self.config.stop_timeout)
+            };
+        }
+    }
+
+    /// Restart: stop gracefully (if running) and spawn once the stop completes.
     pub fn restart(&mut self) -> Result<()> {
-        self.stop(true)?;
-        self.spawn()
+        if self.status.is_running() {
+            self.restart_pending = true;
+            self.stop(true)
+        } else {
+            self.spawn()
+        }
     }
 
     pub fn write_input(&mut self, data: &[u8]) -> Result<()> {
@@ -154,29 +360,236 @@ impl ProcessHandle {
     pub fn drain_output(&mut self) -> bool {
         let mut had_output = false;
 
-        if let Some(ref rx) = self.output_rx {
-            while let Ok(data) = rx.try_recv() {
-                self.screen.process_bytes(&data);
-                had_output = true;
+        let chunks: Vec<Vec<u8>> = match self.output_rx {
+            Some(ref rx) => rx.try_iter().collect(),
+            None => Vec::new(),
+        };
+        for data in chunks {
+            self.screen.process_bytes(&data);
+            let lines = self.lines.push(&data);
+            if self.ready_regex.is_some() {
+                self.match_ready_log(&lines);
             }
+            self.new_lines.extend(lines);
+            had_output = true;
         }
 
         if let Some(ref mut child) = self.child {
             if let Ok(Some(exit_status)) = child.try_wait() {
-                let code: i32 = exit_status.exit_code().try_into().unwrap_or(-1);
-                if code == 0 {
-                    self.status = ProcessStatus::Stopped { exit_code: code };
+                if matches!(self.status, ProcessStatus::Stopping { .. }) {
+                    // Finished by check_stopping once the rest of the group is gone
+                    self.stop_exit = Some(exit_status);
                 } else {
-                    self.status = ProcessStatus::Crashed {};
+                    let exit = ExitInfo::from_exit_status(&exit_status, StopCause::Exited);
+                    self.last_exit_at = Some(exit.at);
+                    self.status = if exit.success() {
+                        ProcessStatus::Stopped { exit }
+                    } else {
+                        ProcessStatus::Crashed { exit }
+                    };
+                    self.master_pty = None;
                 }
                 self.child = None;
-                self.master_pty = None;
+                self.clear_probes();
             }
         }
 
         had_output
     }
 
+    /// Advance the readiness probe; flips `Starting` to `Ready` once it passes.
+    pub fn check_ready(&mut self) {
+        let ProcessStatus::Starting { pid } = self.status else {
+            return;
+        };
+        if let Some(ref mut probe) = self.ready_probe {
+            if probe.poll() == Some(true) {
+                self.status = ProcessStatus::Ready { pid };
+                self.clear_probe();
+            }
+        }
+    }
+
+    /// Advance the health check. Returns true when the failure threshold has
+    /// just been reached and the config asks for a restart.
+    pub fn check_health(&mut self) -> bool {
+        let pid = match self.status {
+            ProcessStatus::Running { pid }
+            | ProcessStatus::Ready { pid }
+            | ProcessStatus::Unhealthy { pid } => pid,
+            // Not checked while still starting
+            _ => return false,
+        };
+        let (threshold, restart) = match self.config.healthcheck {
+            Some(ref hc) => (hc.failure_threshold, hc.restart),
+            None => return false,
+        };
+        let passed = match self.health_probe.as_mut().and_then(|p| p.poll()) {
+            Some(passed) => passed,
+            None => return false,
+        };
+
+        if passed {
+            self.health_failures = 0;
+            if matches!(self.status, ProcessStatus::Unhealthy { .. }) {
+                self.status = if self.config.ready.is_some() {
+                    ProcessStatus::Ready { pid }
+                } else {
+                    ProcessStatus::Running { pid }
+                };
+                self.print_notice("Health check passed");
+            }
+            return false;
+        }
+
+        self.health_failures += 1;
+        if self.health_failures == threshold {
+            self.status = ProcessStatus::Unhealthy { pid };
+            self.print_notice(&format!(
+                "Health check failed {} times in a row{}",
+                threshold,
+                if restart { ", restarting" } else { "" }
+            ));
+            return restart;
+        }
+        false
+    }
+
+    /// How long the current run has been up.
+    pub fn uptime(&self) -> Option<Duration> {
+        if !self.status.is_running() {
+            return None;
+        }
+        self.started_at.and_then(|t| t.elapsed().ok())
+    }
+
+    /// How long ago the process exited, if it is down.
+    pub fn time_since_exit(&self) -> Option<Duration> {
+        if self.status.is_running() {
+            return None;
+        }
+        self.last_exit_at.and_then(|t| t.elapsed().ok())
+    }
+
+    /// Apply the restart policy once the process has exited: schedule a
+    /// restart with exponential backoff, spawn it when due, or give up when
+    /// it keeps crashing.
+    pub fn check_autorestart(&mut self) {
+        let success = match self.status.exit() {
+            Some(exit) => exit.success(),
+            None => return,
+        };
+        let restart = &self.config.restart;
+        if self.crash_looping || !restart.policy.should_restart(success, self.stopped_by_user) {
+            return;
+        }
+
+        let now = Instant::now();
+        let Some(at) = self.next_restart else {
+            let window = restart.window;
+            self.restart_history
+                .retain(|t| now.duration_since(*t) < window);
+            let recent = self.restart_history.len() as u32;
+            if recent >= restart.max_restarts {
+                self.crash_looping = true;
+                self.print_notice(&format!(
+                    "Restarted {} times within {}s, giving up (crash loop)",
+                    recent,
+                    window.as_secs()
+                ));
+            } else {
+                self.next_restart = Some(now + restart.backoff(recent));
+            }
+            return;
+        };
+
+        if now >= at {
+            self.next_restart = None;
+            self.restart_history.push_back(now);
+            if let Err(e) = self.spawn() {
+                self.print_notice(&format!("Failed to restart: {}", e));
+                self.status = ProcessStatus::Crashed {
+                    exit: ExitInfo::unknown(StopCause::Exited),
+                };
+            }
+        }
+    }
+
+    /// Drop a pending restart, both the one after a stop and the backoff
+    /// timer, so that the process stays down.
+    pub fn cancel_restart(&mut self) {
+        self.restart_pending = false;
+        self.next_restart = None;
+    }
+
+    /// Forget backoff and crash-loop state, e.g. after a manual start.
+    pub fn reset_restart_state(&mut self) {
+        self.next_restart = None;
+        self.crash_looping = false;
+        self.restart_history.clear();
+    }
+
+    /// Tell why a waiting process can't start yet, unless that was the last
+    /// thing said.
+    pub fn notice_waiting(&mut self, msg: String) {
+        if self.waiting_notice.as_ref() != Some(&msg) {
+            self.print_notice(&msg);
+            self.waiting_notice = Some(msg);
+        }
+    }
+
+    /// Write a dimmed betterprocs message into the process output.
+    pub fn print_notice(&mut self, msg: &str) {
+        self.screen
+            .process_bytes(format!("\r\n\x1b[2m[betterprocs] {}\x1b[0m\r\n", msg).as_bytes());
+        self.new_lines.push(format!("[betterprocs] {}", msg));
+    }
+
+    /// The environment `spawn` gives the process, for command probes.
+    fn probe_env(&self) -> ProbeEnv {
+        ProbeEnv {
+            cwd: self.config.cwd.clone(),
+            env: self
+                .config
+                .file_env
+                .iter()
+                .chain(&self.config.env)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            clean: self.config.clean_env,
+        }
+    }
+
+    /// Plain-text output lines completed since the last call.
+    pub fn take_lines(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.new_lines)
+    }
+
+    fn match_ready_log(&mut self, lines: &[String]) {
+        let ProcessStatus::Starting { pid } = self.status else {
+            return;
+        };
+        let Some(ref re) = self.ready_regex else {
+            return;
+        };
+        // Also check the unterminated line: prompts often don't end in a newline
+        if lines.iter().any(|l| re.is_match(l)) || re.is_match(&self.lines.partial()) {
+            self.status = ProcessStatus::Ready { pid };
+            self.clear_probe();
+        }
+    }
+
+    fn clear_probe(&mut self) {
+        self.ready_probe = None;
+        self.ready_regex = None;
+    }
+
+    fn clear_probes(&mut self) {
+        self.clear_probe();
+        self.health_probe = None;
+        self.health_failures = 0;
+    }
+
     pub fn resize_pty(&mut self, rows: u16, cols: u16) {
         // Only grow the vt100 screen, never shrink it — shrinking destroys
         // content at the right edge that can't be recovered on re-enlarge.
@@ -197,12 +610,22 @@ impl ProcessHandle {
     }
 }
 
-fn read_pty_output(mut reader: Box<dyn Read + Send>, tx: mpsc::Sender<Vec<u8>>) {
+fn read_pty_output(
+    mut reader: Box<dyn Read + Send>,
+    tx: mpsc::Sender<Vec<u8>>,
+    mut log: Option<LogWriter>,
+) {
     let mut buf = [0u8; 4096];
     loop {
         match reader.read(&mut buf) {
             Ok(0) => break,
             Ok(n) => {
+                // Tee to the log file; stop logging after a write error
+                if let Some(ref mut writer) = log {
+                    if writer.write(&buf[..n]).is_err() {
+                        log = None;
+                    }
+                }
                 if tx.send(buf[..n].to_vec()).is_err() {
                     break;
                 }
@@ -0,0 +1,358 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::de::{self, value::MapAccessDeserializer, MapAccess, Visitor};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::config::{duration, size};
+use crate::terminal::lines::LineBuffer;
+
+/// `log` as written in YAML, globally under `settings` or per process.
+/// Every field is optional so a process can override just some of them.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LogSettings {
+    /// Directory for `<name>.log` files
+    pub dir: Option<PathBuf>,
+    /// Exact file to write (per process), instead of `<dir>/<name>.log`
+    pub path: Option<PathBuf>,
+    /// Rotate once the file would grow past this size
+    #[serde(default, deserialize_with = "size::deserialize_opt")]
+    #[schemars(schema_with = "size::schema")]
+    pub max_size: Option<u64>,
+    /// Rotate once the file is older than this
+    #[serde(default, deserialize_with = "duration::deserialize_opt")]
+    #[schemars(schema_with = "duration::schema")]
+    pub max_age: Option<Duration>,
+    /// Rotated files to keep (`api.log.1` is the newest)
+    pub keep: Option<usize>,
+    /// Write plain text lines instead of the raw terminal bytes
+    pub strip_ansi: Option<bool>,
+    /// Prefix every line with the time it arrived. Off by default, so that
+    /// raw logs stay an exact copy of the output
+    pub timestamps: Option<bool>,
+}
+
+/// `log: true`, `log: false` or a settings map.
+#[derive(Debug, Clone, JsonSchema)]
+#[schemars(untagged)]
+pub enum LogOption {
+    Enabled(bool),
+    Settings(LogSettings),
+}
+
+impl<'de> Deserialize<'de> for LogOption {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct LogVisitor;
+
+        impl<'de> Visitor<'de> for LogVisitor {
+            type Value = LogOption;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("true, false or log settings")
+            }
+
+            fn visit_bool<E: de::Error>(self, enabled: bool) -> std::result::Result<Self::Value, E> {
+                Ok(LogOption::Enabled(enabled))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                LogSettings::deserialize(MapAccessDeserializer::new(map)).map(LogOption::Settings)
+            }
+        }
+
+        deserializer.deserialize_any(LogVisitor)
+    }
+}
+
+impl LogOption {
+    /// Settings for a process given the global option. None means no log file.
+    pub fn resolve(proc: Option<&LogOption>, global: Option<&LogOption>) -> Option<LogSettings> {
+        let global = match global {
+            Some(LogOption::Enabled(true)) => Some(LogSettings::default()),
+            Some(LogOption::Settings(settings)) => Some(settings.clone()),
+            Some(LogOption::Enabled(false)) | None => None,
+        };
+        match proc {
+            None => global,
+            Some(LogOption::Enabled(false)) => None,
+            Some(LogOption::Enabled(true)) => Some(global.unwrap_or_default()),
+            Some(LogOption::Settings(settings)) => {
+                Some(settings.clone().merged_over(&global.unwrap_or_default()))
+            }
+        }
+    }
+}
+
+impl LogSettings {
+    /// Fields set here win over those in `base`.
+    fn merged_over(self, base: &LogSettings) -> LogSettings {
+        LogSettings {
+            dir: self.dir.or_else(|| base.dir.clone()),
+            path: self.path,
+            max_size: self.max_size.or(base.max_size),
+            max_age: self.max_age.or(base.max_age),
+            keep: self.keep.or(base.keep),
+            strip_ansi: self.strip_ansi.or(base.strip_ansi),
+            timestamps: self.timestamps.or(base.timestamps),
+        }
+    }
+
+    pub fn into_config(self, name: &str) -> LogConfig {
+        let path = self.path.unwrap_or_else(|| {
+            self.dir
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_DIR))
+                .join(format!("{}.log", name.replace(['/', '\\'], "_")))
+        });
+        LogConfig {
+            path,
+            max_size: self.max_size,
+            max_age: self.max_age,
+            keep: self.keep.unwrap_or(DEFAULT_KEEP),
+            strip_ansi: self.strip_ansi.unwrap_or(false),
+            timestamps: self.timestamps.unwrap_or(false),
+        }
+    }
+}
+
+const DEFAULT_DIR: &str = ".betterprocs/logs";
+const DEFAULT_KEEP: usize = 5;
+
+/// Resolved log file settings of one process.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogConfig {
+    pub path: PathBuf,
+    pub max_size: Option<u64>,
+    pub max_age: Option<Duration>,
+    pub keep: usize,
+    pub strip_ansi: bool,
+    pub timestamps: bool,
+}
+
+/// Appends a process's output to its log file, rotating as configured.
+/// Runs on the PTY reader thread.
+pub struct LogWriter {
+    config: LogConfig,
+    file: File,
+    size: u64,
+    created: SystemTime,
+    /// Set with `strip_ansi`: turns raw bytes into plain lines
+    lines: Option<LineBuffer>,
+    /// The next raw byte starts a new line (and needs a timestamp)
+    at_line_start: bool,
+}
+
+impl LogWriter {
+    pub fn open(config: LogConfig) -> Result<Self> {
+        if let Some(dir) = config.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+        }
+        let (file, size, created) = open_append(&config.path)
+            .with_context(|| format!("Failed to open log file {}", config.path.display()))?;
+        let lines = config.strip_ansi.then(LineBuffer::new);
+        let mut writer = Self {
+            config,
+            file,
+            size,
+            created,
+            lines,
+            at_line_start: true,
+        };
+        let header = format!("--- started at {} ---\n", Local::now().format("%Y-%m-%d %H:%M:%S"));
+        writer.write_raw(header.as_bytes())?;
+        Ok(writer)
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let stamp = self
+            .config
+            .timestamps
+            .then(|| format!("[{}] ", Local::now().format("%Y-%m-%d %H:%M:%S%.3f")));
+        let stamp = stamp.as_deref().unwrap_or("");
+
+        let mut out = Vec::with_capacity(data.len());
+        match self.lines {
+            Some(ref mut lines) => {
+                for line in lines.push(data) {
+                    out.extend_from_slice(stamp.as_bytes());
+                    out.extend_from_slice(line.as_bytes());
+                    out.push(b'\n');
+                }
+            }
+            None => {
+                for segment in data.split_inclusive(|&b| b == b'\n') {
+                    if self.at_line_start {
+                        out.extend_from_slice(stamp.as_bytes());
+                    }
+                    out.extend_from_slice(segment);
+                    self.at_line_start = segment.ends_with(b"\n");
+                }
+            }
+        }
+        self.write_raw(&out)
+    }
+
+    fn write_raw(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if self.should_rotate(data.len() as u64) {
+            self.rotate()?;
+        }
+        self.file.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+
+    fn should_rotate(&self, incoming: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_big = self
+            .config
+            .max_size
+            .is_some_and(|max| self.size + incoming > max);
+        let too_old = self
+            .config
+            .max_age
+            .is_some_and(|max| self.created.elapsed().is_ok_and(|age| age >= max));
+        too_big || too_old
+    }
+
+    /// api.log -> api.log.1 -> api.log.2 ..., dropping the oldest beyond `keep`.
+    fn rotate(&mut self) -> io::Result<()> {
+        let path = &self.config.path;
+        if self.config.keep == 0 {
+            fs::remove_file(path)?;
+        } else {
+            for i in (1..self.config.keep).rev() {
+                let from = rotated_path(path, i);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(path, i + 1))?;
+                }
+            }
+            fs::rename(path, rotated_path(path, 1))?;
+        }
+        let (file, size, _) = open_append(path)?;
+        self.file = file;
+        self.size = size;
+        self.created = SystemTime::now();
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        // Keep an unterminated last line
+        if let Some(partial) = self.lines.as_ref().map(|l| l.partial()) {
+            if !partial.is_empty() {
+                let _ = self.write_raw(format!("{}\n", partial).as_bytes());
+            }
+        }
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<(File, u64, SystemTime)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let meta = file.metadata()?;
+    let created = meta
+        .created()
+        .or_else(|_| meta.modified())
+        .unwrap_or_else(|_| SystemTime::now());
+    Ok((file, meta.len(), created))
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("betterprocs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_resolve_override() {
+        let global: LogOption = serde_yaml::from_str("dir: logs\nmax_size: 1MB").unwrap();
+        let proc: LogOption = serde_yaml::from_str("strip_ansi: true").unwrap();
+
+        let cfg = LogOption::resolve(Some(&proc), Some(&global)).unwrap().into_config("api");
+        assert_eq!(cfg.path, PathBuf::from("logs/api.log"));
+        assert_eq!(cfg.max_size, Some(1024 * 1024));
+        assert!(cfg.strip_ansi);
+
+        assert!(LogOption::resolve(Some(&LogOption::Enabled(false)), Some(&global)).is_none());
+        assert!(LogOption::resolve(None, None).is_none());
+        let cfg = LogOption::resolve(Some(&LogOption::Enabled(true)), None).unwrap().into_config("web");
+        assert_eq!(cfg.path, PathBuf::from(".betterprocs/logs/web.log"));
+    }
+
+    #[test]
+    fn test_size_rotation() {
+        let dir = temp_dir("rotate");
+        let config = LogConfig {
+            path: dir.join("api.log"),
+            max_size: Some(64),
+            max_age: None,
+            keep: 2,
+            strip_ansi: true,
+            timestamps: false,
+        };
+        let mut writer = LogWriter::open(config).unwrap();
+        for i in 0..20 {
+            writer.write(format!("\x1b[32mline {}\x1b[0m\r\n", i).as_bytes()).unwrap();
+        }
+        drop(writer);
+
+        let current = fs::read_to_string(dir.join("api.log")).unwrap();
+        assert!(current.ends_with("line 19\n"));
+        assert!(!current.contains('\x1b'));
+        assert!(dir.join("api.log.1").exists());
+        assert!(dir.join("api.log.2").exists());
+        assert!(!dir.join("api.log.3").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_raw_timestamps() {
+        let dir = temp_dir("stamps");
+        let config = LogConfig {
+            path: dir.join("web.log"),
+            max_size: None,
+            max_age: None,
+            keep: 1,
+            strip_ansi: false,
+            timestamps: true,
+        };
+        let mut writer = LogWriter::open(config).unwrap();
+        writer.write(b"\x1b[1mone\r\ntw").unwrap();
+        writer.write(b"o\r\n").unwrap();
+        drop(writer);
+
+        let contents = fs::read_to_string(dir.join("web.log")).unwrap();
+        let lines: Vec<&str> = contents.lines().skip(1).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with('[') && lines[0].ends_with("] \x1b[1mone"));
+        assert!(lines[1].ends_with("] two"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
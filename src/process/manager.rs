@@ -1,87 +1,439 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
 
+use super::deps::dependency_depths;
 use super::handle::ProcessHandle;
-use super::types::{ProcessConfig, ProcessStatus};
+use super::types::{ExitInfo, ProcessConfig, ProcessStatus, StopCause};
+use super::usage::UsageSampler;
+use crate::config::reload::ReloadPlan;
+use crate::terminal::merged::MergedLog;
 
 pub struct ProcessManager {
     pub processes: Vec<ProcessHandle>,
+    /// Output lines of all processes, interleaved
+    pub merged: MergedLog,
+    /// Set once shutdown begins: no more starts or automatic restarts
+    shutting_down: bool,
+    /// Remaining shutdown stages, each stopped in parallel
+    shutdown_stages: Vec<Vec<String>>,
+    shutdown_stage: usize,
+    usage_sampler: UsageSampler,
+    /// Removed from the config file: dropped once they have stopped
+    removing: HashSet<String>,
 }
 
 impl ProcessManager {
     pub fn new() -> Self {
         Self {
             processes: Vec::new(),
+            merged: MergedLog::new(10_000),
+            shutting_down: false,
+            shutdown_stages: Vec::new(),
+            shutdown_stage: 0,
+            usage_sampler: UsageSampler::new(),
+            removing: HashSet::new(),
         }
     }
 
+    /// Add a process. Autostart processes are queued (along with their
+    /// dependencies) and spawned by `start_pending` once those are ready.
     pub fn add_process(&mut self, config: ProcessConfig, rows: u16, cols: u16) {
         let handle = ProcessHandle::new(config, rows, cols);
+        let name = handle.config.name.clone();
+        let autostart = handle.config.autostart;
         self.processes.push(handle);
+        if autostart {
+            self.queue_start(&name, &mut HashSet::new());
+        }
     }
 
+    /// Start a process. A running process is respawned right away; otherwise it
+    /// is queued together with any of its dependencies that aren't running yet.
     pub fn start(&mut self, index: usize) -> Result<()> {
-        if let Some(handle) = self.processes.get_mut(index) {
-            handle.spawn()?;
+        if self.shutting_down {
+            return Ok(());
         }
+        let name = match self.processes.get_mut(index) {
+            Some(handle) if handle.status.is_running() => return handle.restart(),
+            Some(handle) => handle.config.name.clone(),
+            None => return Ok(()),
+        };
+        self.queue_start(&name, &mut HashSet::new());
+        self.start_pending();
         Ok(())
     }
 
+    fn queue_start(&mut self, name: &str, visited: &mut HashSet<String>) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+        let Some(handle) = self.processes.iter_mut().find(|h| h.config.name == name) else {
+            return;
+        };
+        if handle.status.is_running() {
+            return;
+        }
+        handle.reset_restart_state();
+        handle.status = ProcessStatus::Waiting;
+        for dep in handle.config.depends_on.clone() {
+            self.queue_start(&dep, visited);
+        }
+    }
+
+    /// Spawn waiting processes whose dependencies are all ready.
+    pub fn start_pending(&mut self) {
+        if self.shutting_down {
+            return;
+        }
+        let ready: HashSet<String> = self
+            .processes
+            .iter()
+            .filter(|h| h.status.is_ready())
+            .map(|h| h.config.name.clone())
+            .collect();
+        // Dependencies that are down and not about to start
+        let down: HashMap<String, &'static str> = self
+            .processes
+            .iter()
+            .filter(|h| !h.status.is_running() && !matches!(h.status, ProcessStatus::Waiting))
+            .map(|h| (h.config.name.clone(), h.status.label()))
+            .collect();
+
+        for handle in &mut self.processes {
+            if !matches!(handle.status, ProcessStatus::Waiting) {
+                continue;
+            }
+            if handle.config.depends_on.iter().all(|d| ready.contains(d)) {
+                if let Err(e) = handle.spawn() {
+                    handle.print_notice(&format!("Failed to start: {}", e));
+                    handle.status = ProcessStatus::Crashed {
+                        exit: ExitInfo::unknown(StopCause::Exited),
+                    };
+                }
+            } else if let Some(dep) = handle
+                .config
+                .depends_on
+                .iter()
+                .find(|d| down.contains_key(*d))
+            {
+                let msg = format!("waiting for {} ({})", dep, down[dep].to_lowercase());
+                handle.notice_waiting(msg);
+            }
+        }
+    }
+
     pub fn stop(&mut self, index: usize) -> Result<()> {
         if let Some(handle) = self.processes.get_mut(index) {
+            handle.stopped_by_user = true;
+            handle.next_restart = None;
             handle.stop(true)?;
         }
         Ok(())
     }
 
+    /// Remove a stopped process that nothing depends on.
+    pub fn remove_process(&mut self, index: usize) -> Result<()> {
+        let Some(handle) = self.processes.get(index) else {
+            return Ok(());
+        };
+        let name = &handle.config.name;
+        if handle.status.is_running() {
+            bail!("Stop \"{}\" before removing it", name);
+        }
+        if let Some(dependent) = self.processes.iter().find(|h| h.config.depends_on.contains(name)) {
+            bail!("\"{}\" depends on \"{}\"", dependent.config.name, name);
+        }
+        self.processes.remove(index);
+        Ok(())
+    }
+
     pub fn force_kill(&mut self, index: usize) -> Result<()> {
         if let Some(handle) = self.processes.get_mut(index) {
+            handle.stopped_by_user = true;
+            handle.next_restart = None;
             handle.stop(false)?;
         }
         Ok(())
     }
 
     pub fn restart(&mut self, index: usize) -> Result<()> {
+        if self.shutting_down {
+            return Ok(());
+        }
         if let Some(handle) = self.processes.get_mut(index) {
+            handle.reset_restart_state();
             handle.restart()?;
         }
         Ok(())
     }
 
+    /// Indices of the processes in a group.
+    pub fn group_indices(&self, group: &str) -> Vec<usize> {
+        (0..self.processes.len())
+            .filter(|&i| self.processes[i].config.group.as_deref() == Some(group))
+            .collect()
+    }
+
+    /// Start every process of a group that isn't running, dependencies first.
+    pub fn start_group(&mut self, group: &str) {
+        if self.shutting_down {
+            return;
+        }
+        let mut visited = HashSet::new();
+        for index in self.group_indices(group) {
+            let name = self.processes[index].config.name.clone();
+            self.queue_start(&name, &mut visited);
+        }
+        self.start_pending();
+    }
+
+    pub fn stop_group(&mut self, group: &str) {
+        for index in self.group_indices(group) {
+            if self.processes[index].status.is_running() {
+                let _ = self.stop(index);
+            }
+        }
+    }
+
+    pub fn force_kill_group(&mut self, group: &str) {
+        for index in self.group_indices(group) {
+            if self.processes[index].status.is_running() {
+                let _ = self.force_kill(index);
+            }
+        }
+    }
+
+    /// Restart the running processes of a group and start the others.
+    pub fn restart_group(&mut self, group: &str) {
+        for index in self.group_indices(group) {
+            if self.processes[index].status.is_running() {
+                let _ = self.restart(index);
+            }
+        }
+        self.start_group(group);
+    }
+
     /// Drain output from all processes. Returns true if any had new output.
     pub fn drain_output(&mut self) -> bool {
         let mut any_output = false;
+        let now = SystemTime::now();
         for handle in &mut self.processes {
             if handle.drain_output() {
                 any_output = true;
             }
+            for line in handle.take_lines() {
+                self.merged.push(&handle.config.name, now, line);
+            }
         }
         any_output
     }
 
-    /// Check for autorestart
+    /// Run readiness probes for processes that are still starting.
+    pub fn check_ready(&mut self) {
+        for handle in &mut self.processes {
+            handle.check_ready();
+        }
+    }
+
+    /// Run health checks and restart processes that crossed their failure threshold.
+    pub fn check_health(&mut self) {
+        for handle in &mut self.processes {
+            if handle.check_health() && !self.shutting_down {
+                let _ = handle.restart();
+            }
+        }
+    }
+
+    /// Advance pending stops (signal escalation, completion).
+    pub fn check_stopping(&mut self) {
+        for handle in &mut self.processes {
+            handle.check_stopping();
+        }
+        if !self.removing.is_empty() {
+            let removing = &mut self.removing;
+            self.processes.retain(|h| {
+                let remove = !h.status.is_running() && removing.remove(&h.config.name);
+                !remove
+            });
+        }
+    }
+
+    /// Apply a reloaded config file: add and remove processes, and restart
+    /// running ones whose command, cwd or env changed. Removed processes
+    /// that are running are stopped first.
+    pub fn apply_reload(&mut self, plan: ReloadPlan, rows: u16, cols: u16) {
+        for name in plan.removed {
+            let Some(index) = self.index_of(&name) else {
+                continue;
+            };
+            if self.processes[index].status.is_running() {
+                let _ = self.stop(index);
+                self.removing.insert(name);
+            } else {
+                self.processes.remove(index);
+            }
+        }
+        for config in plan.updated {
+            if let Some(index) = self.index_of(&config.name) {
+                self.removing.remove(&config.name);
+                self.processes[index].set_config(config);
+            }
+        }
+        for config in plan.changed {
+            if let Some(index) = self.index_of(&config.name) {
+                self.removing.remove(&config.name);
+                let handle = &mut self.processes[index];
+                handle.set_config(config);
+                if handle.status.is_running() {
+                    handle.print_notice("config changed, restarting");
+                    let _ = self.restart(index);
+                }
+            }
+        }
+        for config in plan.added {
+            self.add_process(config, rows, cols);
+        }
+    }
+
+    /// Apply restart policies to exited processes.
     pub fn check_autorestart(&mut self) {
+        if self.shutting_down {
+            return;
+        }
         for handle in &mut self.processes {
-            if handle.config.autorestart && !handle.status.is_running() {
-                if !matches!(handle.status, ProcessStatus::NotStarted) {
-                    let _ = handle.spawn();
+            handle.check_autorestart();
+        }
+    }
+
+    /// Restart processes whose watched files changed. Processes the user
+    /// stopped (or never started) stay down.
+    pub fn check_watches(&mut self) {
+        for index in 0..self.processes.len() {
+            let handle = &mut self.processes[index];
+            let Some(path) = handle.check_watch() else {
+                continue;
+            };
+            let wanted = handle.status.is_running()
+                || handle.status.exit().is_some() && !handle.stopped_by_user;
+            if self.shutting_down || !wanted {
+                continue;
+            }
+            handle.print_notice(&format!("restarting due to change in {}", path.display()));
+            let _ = self.restart(index);
+        }
+    }
+
+    /// Begin an ordered shutdown. Processes are grouped into stages by
+    /// `shutdown_priority` (higher first), then dependency depth (dependents
+    /// before their dependencies). Each stage is stopped in parallel and the
+    /// next one starts once it is down; `advance_shutdown` drives this.
+    pub fn begin_shutdown(&mut self) {
+        if self.shutting_down {
+            return;
+        }
+        self.shutting_down = true;
+
+        let depths = dependency_depths(self.processes.iter().map(|h| &h.config));
+        let mut keyed: Vec<((i32, usize), String)> = self
+            .processes
+            .iter_mut()
+            .map(|h| {
+                if matches!(h.status, ProcessStatus::Waiting) {
+                    h.status = ProcessStatus::NotStarted;
                 }
+                // A restart already under way must not bring it back
+                h.cancel_restart();
+                let depth = depths.get(&h.config.name).copied().unwrap_or(0);
+                ((h.config.shutdown_priority, depth), h.config.name.clone())
+            })
+            .collect();
+        keyed.sort_by_key(|(key, _)| std::cmp::Reverse(*key));
+
+        self.shutdown_stages.clear();
+        let mut last_key = None;
+        for (key, name) in keyed {
+            if last_key != Some(key) {
+                self.shutdown_stages.push(Vec::new());
+                last_key = Some(key);
+            }
+            if let Some(stage) = self.shutdown_stages.last_mut() {
+                stage.push(name);
+            }
+        }
+        self.shutdown_stage = 0;
+        self.stop_stage();
+    }
+
+    /// Move to the next shutdown stage once the current one has stopped.
+    pub fn advance_shutdown(&mut self) {
+        while self.shutting_down && self.shutdown_stage < self.shutdown_stages.len() {
+            let stage = &self.shutdown_stages[self.shutdown_stage];
+            let stage_running = self
+                .processes
+                .iter()
+                .any(|h| stage.contains(&h.config.name) && h.status.is_running());
+            if stage_running {
+                return;
             }
+            self.shutdown_stage += 1;
+            self.stop_stage();
         }
     }
 
-    /// Stop all running processes
-    pub fn stop_all(&mut self) {
+    fn stop_stage(&mut self) {
+        let Some(stage) = self.shutdown_stages.get(self.shutdown_stage) else {
+            return;
+        };
         for handle in &mut self.processes {
-            if handle.status.is_running() {
+            if stage.contains(&handle.config.name) && handle.status.is_running() {
                 let _ = handle.stop(true);
             }
         }
     }
 
+    /// Escalate a shutdown in progress: SIGKILL everything still running.
+    pub fn force_kill_all(&mut self) {
+        self.shutting_down = true;
+        for handle in &mut self.processes {
+            if handle.status.is_running() {
+                let _ = handle.stop(false);
+            }
+        }
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down
+    }
+
+    /// Current shutdown stage (1-based) and the number of stages.
+    pub fn shutdown_progress(&self) -> (usize, usize) {
+        let total = self.shutdown_stages.len();
+        ((self.shutdown_stage + 1).min(total), total)
+    }
+
+    /// Run the ordered shutdown to completion, blocking. Used when the event
+    /// loop is no longer running.
+    pub fn shutdown_blocking(&mut self) {
+        self.begin_shutdown();
+        while !self.all_stopped() {
+            self.drain_output();
+            self.check_stopping();
+            self.advance_shutdown();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+
+    /// Nothing is running or waiting out the backoff before a restart.
     pub fn all_stopped(&self) -> bool {
         self.processes
             .iter()
-            .all(|h| !h.status.is_running())
+            .all(|h| !h.status.is_running() && h.next_restart.is_none())
+    }
+
+    /// Index of the process with this name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.processes.iter().position(|h| h.config.name == name)
     }
 
     pub fn process_count(&self) -> usize {
@@ -109,6 +461,25 @@ impl ProcessManager {
             .unwrap_or(0)
     }
 
+    /// Refresh CPU and memory usage of every running process group,
+    /// at most once per sample interval.
+    pub fn sample_usage(&mut self) {
+        if !self.usage_sampler.due() {
+            return;
+        }
+        let pgids: Vec<u32> = self.processes.iter().filter_map(|h| h.status.pid()).collect();
+        let samples = self.usage_sampler.sample(&pgids);
+        for handle in &mut self.processes {
+            match handle.status.pid() {
+                Some(pid) => {
+                    let (cpu, rss) = samples.get(&pid).copied().unwrap_or_default();
+                    handle.usage.record(cpu, rss);
+                }
+                None => handle.usage.clear_current(),
+            }
+        }
+    }
+
     /// Resize all process PTYs
     pub fn resize_all(&mut self, rows: u16, cols: u16) {
         for handle in &mut self.processes {
@@ -119,6 +490,91 @@ impl ProcessManager {
 
 impl Drop for ProcessManager {
     fn drop(&mut self) {
-        self.stop_all();
+        self.shutdown_blocking();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::restart::RestartPolicy;
+    use std::time::{Duration, Instant};
+
+    /// Drive the manager like the event loop until `done` or a timeout.
+    fn run_until(manager: &mut ProcessManager, done: impl Fn(&ProcessManager) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(manager) && Instant::now() < deadline {
+            manager.drain_output();
+            manager.check_stopping();
+            manager.advance_shutdown();
+            manager.start_pending();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_waiting_on_crashed_dependency() {
+        let mut manager = ProcessManager::new();
+        let db = ProcessConfig {
+            ready: Some(serde_yaml::from_str("log: accepting connections").unwrap()),
+            ..ProcessConfig::test("db", "exit 3")
+        };
+        let web = ProcessConfig::test("web", "sleep 30").depending_on(&["db"]);
+        manager.add_process(db, 24, 80);
+        manager.add_process(web, 24, 80);
+        manager.start_pending();
+
+        // Exits before its readiness probe can pass
+        run_until(&mut manager, |m| m.processes[0].status.exit().is_some());
+        manager.start_pending();
+        assert!(matches!(
+            manager.processes[1].status,
+            ProcessStatus::Waiting
+        ));
+        let notices: Vec<String> = manager.processes[1]
+            .take_lines()
+            .into_iter()
+            .filter(|l| l.contains("waiting for"))
+            .collect();
+        assert_eq!(notices, ["[betterprocs] waiting for db (crashed)"]);
+    }
+
+    #[test]
+    fn test_shutdown_cancels_pending_restart() {
+        let mut manager = ProcessManager::new();
+        manager.add_process(ProcessConfig::test("sleeper", "sleep 30"), 24, 80);
+        manager.start_pending();
+        assert!(manager.processes[0].status.is_running());
+
+        // Quit while the restart is still waiting for the old process to stop
+        manager.restart(0).unwrap();
+        assert!(matches!(
+            manager.processes[0].status,
+            ProcessStatus::Stopping { .. }
+        ));
+        manager.begin_shutdown();
+        manager.restart(0).unwrap();
+        manager.start(0).unwrap();
+
+        run_until(&mut manager, |m| m.all_stopped());
+        assert!(matches!(
+            manager.processes[0].status,
+            ProcessStatus::Stopped { .. }
+        ));
+    }
+
+    #[test]
+    fn test_backoff_is_not_stopped() {
+        let mut manager = ProcessManager::new();
+        let mut config = ProcessConfig::test("flaky", "exit 1");
+        config.restart.policy = RestartPolicy::OnFailure;
+        config.restart.delay = Duration::from_secs(10);
+        manager.add_process(config, 24, 80);
+        manager.start_pending();
+
+        run_until(&mut manager, |m| m.processes[0].status.exit().is_some());
+        manager.check_autorestart();
+        assert!(manager.processes[0].next_restart.is_some());
+        assert!(!manager.all_stopped());
     }
 }
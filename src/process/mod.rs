@@ -1,4 +1,11 @@
+pub mod deps;
 pub mod handle;
+pub mod log;
 pub mod manager;
+pub mod outcome;
+pub mod probe;
+pub mod restart;
 pub mod signal;
 pub mod types;
+pub mod usage;
+pub mod watch;
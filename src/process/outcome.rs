@@ -0,0 +1,207 @@
+use std::io::{self, Write};
+
+use super::handle::ProcessHandle;
+use super::manager::ProcessManager;
+use super::types::{ExitInfo, StopCause};
+
+/// Which processes decide the overall exit code.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SuccessPolicy {
+    /// Succeed only if no process failed
+    #[default]
+    All,
+    /// Use the exit code of the first process to exit
+    First,
+    /// Use the exit code of the last process to exit
+    Last,
+}
+
+/// How a run finishes and what betterprocs exits with, for CI-style use.
+#[derive(Debug, Clone, Default)]
+pub struct ExitOptions {
+    /// Stop everything once this process exits and exit with its code
+    pub exit_code_from: Option<String>,
+    /// Stop everything as soon as a process fails
+    pub kill_others_on_fail: bool,
+    pub success: Option<SuccessPolicy>,
+}
+
+impl ExitOptions {
+    /// Whether any option was given; this implies `--auto-exit` and a summary.
+    pub fn enabled(&self) -> bool {
+        self.exit_code_from.is_some() || self.kill_others_on_fail || self.success.is_some()
+    }
+
+    /// Whether the run is decided and everything should be stopped now.
+    pub fn should_stop_all(&self, pm: &ProcessManager) -> bool {
+        if let Some(ref name) = self.exit_code_from {
+            if pm.processes.iter().any(|h| &h.config.name == name && final_exit(h).is_some()) {
+                return true;
+            }
+        }
+        self.kill_others_on_fail && pm.processes.iter().any(failed)
+    }
+
+    /// Exit code for the whole run, once everything has stopped.
+    pub fn exit_code(&self, pm: &ProcessManager) -> i32 {
+        if let Some(ref name) = self.exit_code_from {
+            return pm
+                .processes
+                .iter()
+                .find(|h| &h.config.name == name)
+                .and_then(|h| h.status.exit())
+                .map_or(1, ExitInfo::exit_code);
+        }
+
+        // Processes stopped by the user or during shutdown don't count
+        let exited = pm
+            .processes
+            .iter()
+            .filter_map(|h| h.status.exit())
+            .filter(|e| e.cause == StopCause::Exited);
+        let decisive = match self.success.unwrap_or_default() {
+            SuccessPolicy::All => return if pm.processes.iter().any(failed) { 1 } else { 0 },
+            SuccessPolicy::First => exited.min_by_key(|e| e.at),
+            SuccessPolicy::Last => exited.max_by_key(|e| e.at),
+        };
+        decisive.map_or(0, ExitInfo::exit_code)
+    }
+
+    /// Print one row per process: status, exit code and how long it ran.
+    pub fn print_summary(&self, pm: &ProcessManager) -> io::Result<()> {
+        let name_width = pm
+            .processes
+            .iter()
+            .map(|h| h.config.name.chars().count())
+            .chain(["PROCESS".len()])
+            .max()
+            .unwrap_or(0);
+
+        let mut out = io::stdout().lock();
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{:<name_width$}  {:<11}  {:<8}  DURATION",
+            "PROCESS", "STATUS", "EXIT"
+        )?;
+        for handle in &pm.processes {
+            let exit = handle.status.exit();
+            let code = exit.map_or("-".to_string(), |e| match e.signal {
+                Some(ref sig) => sig.clone(),
+                None => e.exit_code().to_string(),
+            });
+            let duration = match (handle.started_at, exit) {
+                (Some(start), Some(exit)) => exit
+                    .at
+                    .duration_since(start)
+                    .map_or("-".to_string(), |d| format!("{:.1}s", d.as_secs_f64())),
+                _ => "-".to_string(),
+            };
+            writeln!(
+                out,
+                "{:<name_width$}  {:<11}  {:<8}  {}",
+                handle.config.name,
+                handle.status.label(),
+                code,
+                duration
+            )?;
+        }
+        out.flush()
+    }
+}
+
+/// The exit that ended this process for good: no restart is scheduled.
+fn final_exit(handle: &ProcessHandle) -> Option<&ExitInfo> {
+    handle.status.exit().filter(|_| handle.next_restart.is_none())
+}
+
+/// Exited on its own with a nonzero code or signal, and won't be restarted.
+fn failed(handle: &ProcessHandle) -> bool {
+    final_exit(handle).is_some_and(|e| e.cause == StopCause::Exited && !e.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::types::{ProcessConfig, ProcessStatus};
+    use std::time::{Duration, SystemTime};
+
+    fn manager(exits: &[(&str, Option<i32>, StopCause, u64)]) -> ProcessManager {
+        let mut pm = ProcessManager::new();
+        let base = SystemTime::now();
+        for &(name, code, cause, secs) in exits {
+            pm.add_process(
+                ProcessConfig {
+                    name: name.to_string(),
+                    command: "true".to_string(),
+                    ..Default::default()
+                },
+                24,
+                80,
+            );
+            let exit = ExitInfo {
+                code,
+                signal: None,
+                cause,
+                at: base + Duration::from_secs(secs),
+            };
+            let handle = pm.processes.last_mut().unwrap();
+            handle.status = if exit.success() || cause != StopCause::Exited {
+                ProcessStatus::Stopped { exit }
+            } else {
+                ProcessStatus::Crashed { exit }
+            };
+        }
+        pm
+    }
+
+    #[test]
+    fn test_success_policies() {
+        let pm = manager(&[
+            ("build", Some(2), StopCause::Exited, 1),
+            ("test", Some(0), StopCause::Exited, 2),
+            ("server", Some(0), StopCause::Manager, 3),
+        ]);
+        let mut opts = ExitOptions::default();
+        assert_eq!(opts.exit_code(&pm), 1);
+        opts.success = Some(SuccessPolicy::First);
+        assert_eq!(opts.exit_code(&pm), 2);
+        opts.success = Some(SuccessPolicy::Last);
+        assert_eq!(opts.exit_code(&pm), 0);
+    }
+
+    #[test]
+    fn test_exit_code_from() {
+        let pm = manager(&[("test", Some(3), StopCause::Exited, 1)]);
+        let opts = ExitOptions {
+            exit_code_from: Some("test".to_string()),
+            ..Default::default()
+        };
+        assert!(opts.should_stop_all(&pm));
+        assert_eq!(opts.exit_code(&pm), 3);
+    }
+
+    #[test]
+    fn test_kill_others_waits_for_restart() {
+        let mut pm = manager(&[("api", Some(1), StopCause::Exited, 1)]);
+        let opts = ExitOptions {
+            kill_others_on_fail: true,
+            ..Default::default()
+        };
+        pm.processes[0].next_restart = Some(std::time::Instant::now());
+        assert!(!opts.should_stop_all(&pm));
+        pm.processes[0].next_restart = None;
+        assert!(opts.should_stop_all(&pm));
+    }
+
+    #[test]
+    fn test_signal_exit_code() {
+        let exit = ExitInfo {
+            code: None,
+            signal: Some("SIGTERM".to_string()),
+            cause: StopCause::Exited,
+            at: SystemTime::now(),
+        };
+        assert_eq!(exit.exit_code(), 143);
+    }
+}
@@ -0,0 +1,460 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use crate::config::duration;
+use crate::config::env::kept_env;
+
+/// How to tell that a process is actually serving.
+#[derive(Debug, Clone)]
+pub enum Probe {
+    /// TCP connect succeeds: a port number (localhost) or "host:port"
+    Tcp(TcpTarget),
+    /// HTTP GET returns a 2xx status
+    Http(String),
+    /// A line of output matches this regex
+    Log(String),
+    /// A shell command exits 0
+    Command(String),
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum TcpTarget {
+    Port(u16),
+    Address(String),
+}
+
+impl TcpTarget {
+    fn address(&self) -> String {
+        match self {
+            TcpTarget::Port(port) => format!("127.0.0.1:{}", port),
+            TcpTarget::Address(addr) => addr.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(try_from = "RawProbeConfig")]
+#[schemars(with = "RawProbeConfig")]
+pub struct ProbeConfig {
+    pub probe: Probe,
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+/// `ready` as written in YAML: the probe is whichever of `tcp`, `http`,
+/// `log` and `command` is set.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(rename = "ProbeConfig")]
+struct RawProbeConfig {
+    /// TCP connect succeeds: a port number (localhost) or "host:port"
+    tcp: Option<TcpTarget>,
+    /// HTTP GET returns a 2xx status
+    http: Option<String>,
+    /// A line of output matches this regex
+    log: Option<String>,
+    /// A shell command exits 0
+    command: Option<String>,
+    /// Time between attempts
+    #[serde(default = "default_interval", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    interval: Duration,
+    /// Time a single attempt may take before it counts as failed
+    #[serde(default = "default_timeout", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    timeout: Duration,
+}
+
+impl TryFrom<RawProbeConfig> for ProbeConfig {
+    type Error = String;
+
+    fn try_from(raw: RawProbeConfig) -> std::result::Result<Self, String> {
+        Ok(ProbeConfig {
+            probe: probe("ready", raw.tcp, raw.http, raw.log, raw.command)?,
+            interval: raw.interval,
+            timeout: raw.timeout,
+        })
+    }
+}
+
+/// The one probe that is set in `field`.
+fn probe(
+    field: &str,
+    tcp: Option<TcpTarget>,
+    http: Option<String>,
+    log: Option<String>,
+    command: Option<String>,
+) -> std::result::Result<Probe, String> {
+    let mut probes: Vec<Probe> = [
+        tcp.map(Probe::Tcp),
+        http.map(Probe::Http),
+        log.map(Probe::Log),
+        command.map(Probe::Command),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    match probes.len() {
+        0 => Err(format!(
+            "`{}` needs one of `tcp`, `http`, `log` or `command`",
+            field
+        )),
+        1 => Ok(probes.remove(0)),
+        _ => Err(format!(
+            "`{}` can only have one of `tcp`, `http`, `log` and `command`",
+            field
+        )),
+    }
+}
+
+fn default_interval() -> Duration {
+    Duration::from_millis(500)
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+/// Periodic liveness check for a process that is already up.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(try_from = "RawHealthcheckConfig")]
+#[schemars(with = "RawHealthcheckConfig")]
+pub struct HealthcheckConfig {
+    pub probe: Probe,
+    pub interval: Duration,
+    pub timeout: Duration,
+    /// Consecutive failures before the process is marked unhealthy
+    pub failure_threshold: u32,
+    /// Restart the process once it is unhealthy
+    pub restart: bool,
+}
+
+/// `healthcheck` as written in YAML.
+#[derive(Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RawHealthcheckConfig {
+    /// TCP connect succeeds: a port number (localhost) or "host:port"
+    tcp: Option<TcpTarget>,
+    /// HTTP GET returns a 2xx status
+    http: Option<String>,
+    /// Not allowed in health checks, but parsed to say so
+    log: Option<String>,
+    /// A shell command exits 0
+    command: Option<String>,
+    /// Time between checks
+    #[serde(default = "default_health_interval", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    interval: Duration,
+    /// Time a single check may take before it counts as failed
+    #[serde(default = "default_timeout", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    timeout: Duration,
+    /// Consecutive failures before the process is marked unhealthy
+    #[serde(default = "default_failure_threshold")]
+    failure_threshold: u32,
+    /// Restart the process once it is unhealthy
+    #[serde(default)]
+    restart: bool,
+}
+
+impl TryFrom<RawHealthcheckConfig> for HealthcheckConfig {
+    type Error = String;
+
+    fn try_from(raw: RawHealthcheckConfig) -> std::result::Result<Self, String> {
+        Ok(HealthcheckConfig {
+            probe: probe("healthcheck", raw.tcp, raw.http, raw.log, raw.command)?,
+            interval: raw.interval,
+            timeout: raw.timeout,
+            failure_threshold: raw.failure_threshold,
+            restart: raw.restart,
+        })
+    }
+}
+
+fn default_health_interval() -> Duration {
+    Duration::from_secs(10)
+}
+
+fn default_failure_threshold() -> u32 {
+    3
+}
+
+impl HealthcheckConfig {
+    pub fn validate(&self) -> Result<()> {
+        if matches!(self.probe, Probe::Log(_)) {
+            bail!("log probes can only be used for readiness, not health checks");
+        }
+        if self.failure_threshold == 0 {
+            bail!("failure_threshold must be at least 1");
+        }
+        self.probe_config().validate()
+    }
+
+    pub fn probe_config(&self) -> ProbeConfig {
+        ProbeConfig {
+            probe: self.probe.clone(),
+            interval: self.interval,
+            timeout: self.timeout,
+        }
+    }
+}
+
+impl ProbeConfig {
+    /// Catch config mistakes (bad regex, malformed URL) before anything starts.
+    pub fn validate(&self) -> Result<()> {
+        match &self.probe {
+            Probe::Log(pattern) => {
+                Regex::new(pattern).with_context(|| format!("Invalid log regex \"{}\"", pattern))?;
+            }
+            Probe::Http(url) => {
+                parse_http_url(url)?;
+            }
+            Probe::Tcp(_) | Probe::Command(_) => {}
+        }
+        Ok(())
+    }
+
+    /// The compiled regex for log probes.
+    pub fn log_regex(&self) -> Option<Regex> {
+        match &self.probe {
+            Probe::Log(pattern) => Regex::new(pattern).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Context a command probe runs in — the same as the probed process.
+#[derive(Clone)]
+pub struct ProbeEnv {
+    pub cwd: Option<PathBuf>,
+    /// Variables from env files and `env`, set on top of the inherited ones
+    pub env: HashMap<String, String>,
+    /// Don't inherit betterprocs' environment (`clean_env`)
+    pub clean: bool,
+}
+
+/// Periodically runs a probe on a background thread and reports the result.
+/// Log probes are not run here — the handle matches them against its output.
+pub struct ProbeRunner {
+    config: ProbeConfig,
+    env: ProbeEnv,
+    next_attempt: Instant,
+    in_flight: Option<mpsc::Receiver<bool>>,
+}
+
+impl ProbeRunner {
+    pub fn new(config: ProbeConfig, env: ProbeEnv) -> Self {
+        Self {
+            config,
+            env,
+            next_attempt: Instant::now(),
+            in_flight: None,
+        }
+    }
+
+    /// Hold off the first attempt, e.g. to give a fresh process time to boot.
+    pub fn delay_first_attempt(mut self, delay: Duration) -> Self {
+        self.next_attempt = Instant::now() + delay;
+        self
+    }
+
+    /// Poll for a finished attempt, starting a new one when due.
+    /// Returns the result of an attempt that completed since the last poll.
+    pub fn poll(&mut self) -> Option<bool> {
+        if let Some(ref rx) = self.in_flight {
+            match rx.try_recv() {
+                Ok(passed) => {
+                    self.in_flight = None;
+                    self.next_attempt = Instant::now() + self.config.interval;
+                    return Some(passed);
+                }
+                Err(mpsc::TryRecvError::Empty) => return None,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.in_flight = None;
+                    self.next_attempt = Instant::now() + self.config.interval;
+                    return Some(false);
+                }
+            }
+        }
+
+        if matches!(self.config.probe, Probe::Log(_)) || Instant::now() < self.next_attempt {
+            return None;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let probe = self.config.probe.clone();
+        let timeout = self.config.timeout;
+        let env = self.env.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(run_probe(&probe, timeout, &env));
+        });
+        self.in_flight = Some(rx);
+        None
+    }
+}
+
+/// Run a single probe attempt, blocking for at most roughly `timeout`.
+pub fn run_probe(probe: &Probe, timeout: Duration, env: &ProbeEnv) -> bool {
+    match probe {
+        Probe::Tcp(target) => check_tcp(&target.address(), timeout),
+        Probe::Http(url) => check_http(url, timeout).unwrap_or(false),
+        Probe::Command(cmd) => check_command(cmd, timeout, env),
+        Probe::Log(_) => false,
+    }
+}
+
+fn connect(addr: &str, timeout: Duration) -> Option<TcpStream> {
+    addr.to_socket_addrs()
+        .ok()?
+        .find_map(|sock| TcpStream::connect_timeout(&sock, timeout).ok())
+}
+
+fn check_tcp(addr: &str, timeout: Duration) -> bool {
+    connect(addr, timeout).is_some()
+}
+
+/// Split "http://host:port/path" into ("host:port", "host", "/path").
+fn parse_http_url(url: &str) -> Result<(String, String, String)> {
+    let rest = match url.strip_prefix("http://") {
+        Some(rest) => rest,
+        None => bail!("Only http:// URLs are supported in probes, got \"{}\"", url),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        bail!("Missing host in URL \"{}\"", url);
+    }
+    let host = authority.split(':').next().unwrap_or(authority).to_string();
+    let addr = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    Ok((addr, host, path.to_string()))
+}
+
+fn check_http(url: &str, timeout: Duration) -> Result<bool> {
+    let (addr, host, path) = parse_http_url(url)?;
+    let mut stream = connect(&addr, timeout).context("connect failed")?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        path, host
+    )?;
+
+    // Only the status line matters: "HTTP/1.1 200 OK"
+    let mut buf = [0u8; 64];
+    let n = stream.read(&mut buf)?;
+    let head = String::from_utf8_lossy(&buf[..n]);
+    let code = head.split_whitespace().nth(1).unwrap_or("");
+    Ok(code.len() == 3 && code.starts_with('2'))
+}
+
+fn check_command(cmd: &str, timeout: Duration, env: &ProbeEnv) -> bool {
+    let mut command = Command::new("sh");
+    if env.clean {
+        command.env_clear().envs(kept_env());
+    }
+    command
+        .args(["-c", cmd])
+        .envs(&env.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    if let Some(ref cwd) = env.cwd {
+        command.current_dir(cwd);
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(_) => return false,
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_probe_config() {
+        let cfg: ProbeConfig = serde_yaml::from_str("tcp: 5432\ninterval: 1s").unwrap();
+        assert!(matches!(cfg.probe, Probe::Tcp(TcpTarget::Port(5432))));
+        assert_eq!(cfg.interval, Duration::from_secs(1));
+
+        let cfg: ProbeConfig = serde_yaml::from_str("log: 'listening on \\d+'").unwrap();
+        assert!(cfg.log_regex().unwrap().is_match("listening on 3000"));
+
+        assert!(serde_yaml::from_str::<ProbeConfig>("interval: 1s").is_err());
+        assert!(serde_yaml::from_str::<ProbeConfig>("tcp: 5432\nhttp: http://localhost/").is_err());
+        assert!(serde_yaml::from_str::<ProbeConfig>("tcp: 5432\nintervall: 1s").is_err());
+    }
+
+    #[test]
+    fn test_parse_healthcheck_config() {
+        let cfg: HealthcheckConfig =
+            serde_yaml::from_str("http: http://localhost:3000/\nfailure_threshold: 5\nrestart: true")
+                .unwrap();
+        assert_eq!(cfg.interval, Duration::from_secs(10));
+        assert_eq!(cfg.failure_threshold, 5);
+        assert!(cfg.restart);
+
+        let cfg: HealthcheckConfig = serde_yaml::from_str("log: started").unwrap();
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_http_url() {
+        let (addr, host, path) = parse_http_url("http://localhost:8080/health").unwrap();
+        assert_eq!(addr, "localhost:8080");
+        assert_eq!(host, "localhost");
+        assert_eq!(path, "/health");
+        assert!(parse_http_url("https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_command_probe() {
+        let mut env = ProbeEnv {
+            cwd: None,
+            env: HashMap::new(),
+            clean: false,
+        };
+        assert!(run_probe(&Probe::Command("true".into()), Duration::from_secs(2), &env));
+        assert!(!run_probe(&Probe::Command("false".into()), Duration::from_secs(2), &env));
+
+        // cargo sets CARGO_MANIFEST_DIR for tests; a clean env drops it
+        let check = Probe::Command("test \"$FOO\" = bar && test -z \"$CARGO_MANIFEST_DIR\"".into());
+        env.env.insert("FOO".to_string(), "bar".to_string());
+        assert!(!run_probe(&check, Duration::from_secs(2), &env));
+        env.clean = true;
+        assert!(run_probe(&check, Duration::from_secs(2), &env));
+    }
+}
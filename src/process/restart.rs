@@ -0,0 +1,114 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::config::duration;
+
+/// When a process that exited should be started again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Leave it down
+    #[default]
+    Never,
+    /// Restart after a nonzero exit or a crash
+    OnFailure,
+    /// Restart after every exit (the old `autorestart: true`)
+    Always,
+    /// Deprecated: the same as `always`, since a manual stop holds under
+    /// every policy. Warned about when the config loads.
+    UnlessStopped,
+}
+
+impl RestartPolicy {
+    /// A manual stop holds under every policy, until the next manual start.
+    pub fn should_restart(&self, success: bool, stopped_by_user: bool) -> bool {
+        if stopped_by_user {
+            return false;
+        }
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !success,
+            RestartPolicy::Always | RestartPolicy::UnlessStopped => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RestartConfig {
+    #[serde(default)]
+    pub policy: RestartPolicy,
+    /// Delay before the first restart; doubles with every restart in the window
+    #[serde(default = "default_delay", deserialize_with = "duration::deserialize")]
+    pub delay: Duration,
+    /// Upper bound for the backoff delay
+    #[serde(default = "default_max_delay", deserialize_with = "duration::deserialize")]
+    pub max_delay: Duration,
+    /// Restarts allowed within `window` before the process counts as crash-looping
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    #[serde(default = "default_window", deserialize_with = "duration::deserialize")]
+    pub window: Duration,
+}
+
+pub fn default_delay() -> Duration {
+    Duration::from_secs(1)
+}
+
+pub fn default_max_delay() -> Duration {
+    Duration::from_secs(30)
+}
+
+pub fn default_max_restarts() -> u32 {
+    5
+}
+
+pub fn default_window() -> Duration {
+    Duration::from_secs(60)
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            policy: RestartPolicy::Never,
+            delay: default_delay(),
+            max_delay: default_max_delay(),
+            max_restarts: default_max_restarts(),
+            window: default_window(),
+        }
+    }
+}
+
+impl RestartConfig {
+    /// Backoff before the next restart, given how many restarts already
+    /// happened within the window.
+    pub fn backoff(&self, recent_restarts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(recent_restarts.min(16));
+        self.delay.saturating_mul(factor).min(self.max_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_restart() {
+        assert!(!RestartPolicy::Never.should_restart(false, false));
+        assert!(RestartPolicy::OnFailure.should_restart(false, false));
+        assert!(!RestartPolicy::OnFailure.should_restart(true, false));
+        assert!(!RestartPolicy::OnFailure.should_restart(false, true));
+        assert!(RestartPolicy::Always.should_restart(true, false));
+        assert!(!RestartPolicy::Always.should_restart(true, true));
+        assert!(RestartPolicy::UnlessStopped.should_restart(true, false));
+        assert!(!RestartPolicy::UnlessStopped.should_restart(false, true));
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let cfg = RestartConfig::default();
+        assert_eq!(cfg.backoff(0), Duration::from_secs(1));
+        assert_eq!(cfg.backoff(3), Duration::from_secs(8));
+        assert_eq!(cfg.backoff(10), Duration::from_secs(30));
+    }
+}
@@ -1,24 +1,122 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
+use schemars::{json_schema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer};
+use std::ffi::CStr;
+use std::str::FromStr;
 
-/// Send SIGTERM to the entire process group (not just the shell).
+/// Send a signal to the entire process group (not just the shell).
 /// This is the core fix over mprocs — killpg hits the full process tree.
-pub fn terminate_process_group(pid: u32) -> Result<()> {
+pub fn signal_process_group(pid: u32, sig: Signal) -> Result<()> {
     let pgid = Pid::from_raw(pid as i32);
-    signal::killpg(pgid, Signal::SIGTERM)?;
+    signal::killpg(pgid, sig)?;
     Ok(())
 }
 
-/// Force-kill the entire process group with SIGKILL.
-pub fn force_kill_process_group(pid: u32) -> Result<()> {
-    let pgid = Pid::from_raw(pid as i32);
-    signal::killpg(pgid, Signal::SIGKILL)?;
-    Ok(())
+/// Check if any process in the group is still alive.
+pub fn is_group_alive(pid: u32) -> bool {
+    // Sending signal 0 checks if the group exists without actually sending a signal
+    signal::killpg(Pid::from_raw(pid as i32), None).is_ok()
+}
+
+/// Parse a signal name: "TERM", "SIGTERM" and "term" are all accepted.
+pub fn parse_signal(name: &str) -> Result<Signal> {
+    let upper = name.trim().to_uppercase();
+    let full = if upper.starts_with("SIG") {
+        upper
+    } else {
+        format!("SIG{}", upper)
+    };
+    Signal::from_str(&full).map_err(|_| anyhow!("Unknown signal \"{}\"", name))
+}
+
+/// Short display name without the SIG prefix, e.g. "TERM".
+pub fn signal_name(sig: Signal) -> &'static str {
+    let name = sig.as_str();
+    name.strip_prefix("SIG").unwrap_or(name)
+}
+
+/// portable-pty reports a terminating signal only by its strsignal(3)
+/// description ("Segmentation fault"); map it back to the signal.
+pub fn signal_from_description(desc: &str) -> Option<Signal> {
+    Signal::iterator().find(|&sig| {
+        // SAFETY: strsignal returns a pointer to a static or thread-local string
+        let ptr = unsafe { nix::libc::strsignal(sig as i32) };
+        !ptr.is_null() && unsafe { CStr::from_ptr(ptr) }.to_string_lossy() == desc
+    })
+}
+
+pub fn default_stop_signals() -> Vec<Signal> {
+    vec![Signal::SIGTERM]
 }
 
-/// Check if a process is still alive.
-pub fn is_process_alive(pid: u32) -> bool {
-    // Sending signal 0 checks if process exists without actually sending a signal
-    signal::kill(Pid::from_raw(pid as i32), None).is_ok()
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSignals {
+    One(String),
+    Sequence(Vec<String>),
+}
+
+/// Deserialize `stop_signal`: a single signal name or a sequence to escalate through.
+pub fn deserialize_signals<'de, D>(deserializer: D) -> std::result::Result<Vec<Signal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let names = match RawSignals::deserialize(deserializer)? {
+        RawSignals::One(name) => vec![name],
+        RawSignals::Sequence(names) => names,
+    };
+    if names.is_empty() {
+        return Err(serde::de::Error::custom("stop_signal must name at least one signal"));
+    }
+    names
+        .iter()
+        .map(|n| parse_signal(n).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// JSON Schema for `stop_signal`.
+pub fn signals_schema(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "anyOf": [
+            { "type": "string" },
+            { "type": "array", "items": { "type": "string" }, "minItems": 1 }
+        ],
+        "examples": ["TERM", ["INT", "TERM"]]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("TERM").unwrap(), Signal::SIGTERM);
+        assert_eq!(parse_signal("sigint").unwrap(), Signal::SIGINT);
+        assert_eq!(parse_signal("QUIT").unwrap(), Signal::SIGQUIT);
+        assert!(parse_signal("NOPE").is_err());
+    }
+
+    #[test]
+    fn test_signal_from_description() {
+        let ptr = unsafe { nix::libc::strsignal(Signal::SIGSEGV as i32) };
+        let desc = unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned();
+        assert_eq!(signal_from_description(&desc), Some(Signal::SIGSEGV));
+        assert_eq!(signal_from_description("not a signal"), None);
+    }
+
+    #[test]
+    fn test_deserialize_sequence() {
+        #[derive(Deserialize)]
+        struct Cfg {
+            #[serde(deserialize_with = "deserialize_signals")]
+            stop_signal: Vec<Signal>,
+        }
+        let cfg: Cfg = serde_yaml::from_str("stop_signal: HUP").unwrap();
+        assert_eq!(cfg.stop_signal, vec![Signal::SIGHUP]);
+        let cfg: Cfg = serde_yaml::from_str("stop_signal: [INT, TERM]").unwrap();
+        assert_eq!(cfg.stop_signal, vec![Signal::SIGINT, Signal::SIGTERM]);
+    }
 }
@@ -1,36 +1,175 @@
+use nix::sys::signal::Signal;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use super::log::LogConfig;
+use super::probe::{HealthcheckConfig, ProbeConfig};
+use super::restart::RestartConfig;
+use super::watch::WatchConfig;
+use super::signal::{
+    default_stop_signals, deserialize_signals, parse_signal, signal_from_description,
+};
+use crate::config::duration;
 
 #[derive(Debug, Clone)]
 pub enum ProcessStatus {
     NotStarted,
+    /// Queued to start once its dependencies are ready
+    Waiting,
+    /// Spawned, but the readiness probe hasn't passed yet
+    Starting { pid: u32 },
+    /// Readiness probe passed
+    Ready { pid: u32 },
+    /// Spawned, no readiness probe configured
     Running { pid: u32 },
-    Stopped { exit_code: i32 },
-    Crashed {},
+    /// Health check failed `failure_threshold` times in a row
+    Unhealthy { pid: u32 },
+    /// Stop signal sent, waiting for the process group to exit
+    Stopping { pid: u32 },
+    /// Exited successfully, or was stopped on request
+    Stopped { exit: ExitInfo },
+    /// Exited on its own with a nonzero code or signal, or failed to start
+    Crashed { exit: ExitInfo },
+}
+
+/// Why a process is no longer running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopCause {
+    /// It exited on its own
+    Exited,
+    /// Stopped or killed by the user
+    User,
+    /// Stopped by betterprocs: restart, health check or shutdown
+    Manager,
+}
+
+/// How and when a process ended.
+#[derive(Debug, Clone)]
+pub struct ExitInfo {
+    /// Exit code, if the process exited rather than being killed by a signal
+    pub code: Option<i32>,
+    /// Terminating signal, e.g. "SIGSEGV"
+    pub signal: Option<String>,
+    pub cause: StopCause,
+    pub at: SystemTime,
+}
+
+impl ExitInfo {
+    pub fn from_exit_status(status: &portable_pty::ExitStatus, cause: StopCause) -> Self {
+        let signal = status.signal().map(|desc| match signal_from_description(desc) {
+            Some(sig) => sig.as_str().to_string(),
+            None => desc.to_string(),
+        });
+        Self {
+            code: match signal {
+                Some(_) => None,
+                None => status.exit_code().try_into().ok(),
+            },
+            signal,
+            cause,
+            at: SystemTime::now(),
+        }
+    }
+
+    /// An end without an exit status, e.g. the process never spawned.
+    pub fn unknown(cause: StopCause) -> Self {
+        Self {
+            code: None,
+            signal: None,
+            cause,
+            at: SystemTime::now(),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.signal.is_none() && self.code == Some(0)
+    }
+
+    pub fn user_initiated(&self) -> bool {
+        self.cause == StopCause::User
+    }
+
+    /// Exit code in shell convention: 128 + signal number when killed by a
+    /// signal, 1 when the status is unknown.
+    pub fn exit_code(&self) -> i32 {
+        match (&self.signal, self.code) {
+            (Some(sig), _) => parse_signal(sig).map(|s| 128 + s as i32).unwrap_or(1),
+            (None, Some(code)) => code,
+            (None, None) => 1,
+        }
+    }
+
+    /// Short description: "exit 1", "SIGSEGV".
+    pub fn describe(&self) -> String {
+        match (&self.signal, self.code) {
+            (Some(sig), _) => sig.clone(),
+            (None, Some(code)) => format!("exit {}", code),
+            (None, None) => "no exit status".to_string(),
+        }
+    }
 }
 
 impl ProcessStatus {
     pub fn is_running(&self) -> bool {
-        matches!(self, ProcessStatus::Running { .. })
+        self.pid().is_some()
+    }
+
+    /// Whether dependents of this process may be started.
+    pub fn is_ready(&self) -> bool {
+        matches!(
+            self,
+            ProcessStatus::Running { .. } | ProcessStatus::Ready { .. }
+        )
+    }
+
+    /// PID of the live process, if any.
+    pub fn pid(&self) -> Option<u32> {
+        match self {
+            ProcessStatus::Starting { pid }
+            | ProcessStatus::Ready { pid }
+            | ProcessStatus::Running { pid }
+            | ProcessStatus::Unhealthy { pid }
+            | ProcessStatus::Stopping { pid } => Some(*pid),
+            _ => None,
+        }
     }
 
-    /// Sort priority: Running=0 (first), Crashed=1, Stopped=2, NotStarted=3
+    /// Sort priority: Running=0 (first), Waiting=1, Crashed=2, Stopped=3, NotStarted=4
     pub fn sort_order(&self) -> u8 {
         match self {
-            ProcessStatus::Running { .. } => 0,
-            ProcessStatus::Crashed { .. } => 1,
-            ProcessStatus::Stopped { .. } => 2,
-            ProcessStatus::NotStarted => 3,
+            ProcessStatus::Starting { .. }
+            | ProcessStatus::Ready { .. }
+            | ProcessStatus::Running { .. }
+            | ProcessStatus::Unhealthy { .. }
+            | ProcessStatus::Stopping { .. } => 0,
+            ProcessStatus::Waiting => 1,
+            ProcessStatus::Crashed { .. } => 2,
+            ProcessStatus::Stopped { .. } => 3,
+            ProcessStatus::NotStarted => 4,
+        }
+    }
+
+    /// Exit details once the process has ended.
+    pub fn exit(&self) -> Option<&ExitInfo> {
+        match self {
+            ProcessStatus::Stopped { exit } | ProcessStatus::Crashed { exit } => Some(exit),
+            _ => None,
         }
     }
 
     pub fn label(&self) -> &'static str {
         match self {
             ProcessStatus::NotStarted => "NOT STARTED",
+            ProcessStatus::Waiting => "WAITING",
+            ProcessStatus::Starting { .. } => "STARTING",
+            ProcessStatus::Ready { .. } => "READY",
             ProcessStatus::Running { .. } => "RUNNING",
-            ProcessStatus::Stopped { exit_code: 0, .. } => "STOPPED",
-            ProcessStatus::Stopped { .. } => "EXITED",
+            ProcessStatus::Unhealthy { .. } => "UNHEALTHY",
+            ProcessStatus::Stopping { .. } => "STOPPING",
+            ProcessStatus::Stopped { exit } if exit.cause == StopCause::Exited => "EXITED",
+            ProcessStatus::Stopped { .. } => "STOPPED",
             ProcessStatus::Crashed { .. } => "CRASHED",
         }
     }
@@ -45,11 +184,93 @@ pub struct ProcessConfig {
     pub cwd: Option<PathBuf>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Variables from env files, set before `env`
+    #[serde(default)]
+    pub file_env: HashMap<String, String>,
+    /// Don't inherit betterprocs' environment
+    #[serde(default)]
+    pub clean_env: bool,
     #[serde(default = "default_true")]
     pub autostart: bool,
     #[serde(default)]
-    pub autorestart: bool,
+    pub restart: RestartConfig,
     pub port: Option<u16>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    pub ready: Option<ProbeConfig>,
+    pub healthcheck: Option<HealthcheckConfig>,
+    /// Signals sent in turn on stop, `stop_timeout` apart, before SIGKILL
+    #[serde(
+        default = "default_stop_signals",
+        deserialize_with = "deserialize_signals"
+    )]
+    pub stop_signals: Vec<Signal>,
+    #[serde(
+        default = "default_stop_timeout",
+        deserialize_with = "duration::deserialize"
+    )]
+    pub stop_timeout: Duration,
+    /// Processes with a higher priority are stopped first on quit
+    #[serde(default)]
+    pub shutdown_priority: i32,
+    /// Persist output to a log file
+    pub log: Option<LogConfig>,
+    /// Restart when these files change
+    pub watch: Option<WatchConfig>,
+    /// Group shown as a header in the process list
+    pub group: Option<String>,
+    /// What `command`, `cmd`, `cwd` and `env` were interpolated from; None
+    /// when they were used as given
+    #[serde(skip)]
+    pub raw: Option<RawCommand>,
+}
+
+/// `shell`, `cmd`, `cwd` and `env` as written in the config file, before
+/// `${VAR}` interpolation. Edited and saved instead of the resolved values,
+/// so that references (and the secrets behind them) stay out of the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawCommand {
+    pub command: String,
+    pub cmd: Option<Vec<String>>,
+    pub cwd: Option<PathBuf>,
+    pub env: HashMap<String, String>,
+}
+
+impl ProcessConfig {
+    /// The uninterpolated command, cwd and env.
+    pub fn raw_command(&self) -> RawCommand {
+        match self.raw {
+            Some(ref raw) => raw.clone(),
+            None => RawCommand {
+                command: self.command.clone(),
+                cmd: self.cmd.clone(),
+                cwd: self.cwd.clone(),
+                env: self.env.clone(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+impl ProcessConfig {
+    /// A process running `command` with everything else at its default,
+    /// for tests.
+    pub fn test(name: &str, command: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn depending_on(mut self, deps: &[&str]) -> Self {
+        self.depends_on = deps.iter().map(|d| d.to_string()).collect();
+        self
+    }
+}
+
+pub fn default_stop_timeout() -> Duration {
+    Duration::from_secs(5)
 }
 
 fn default_true() -> bool {
@@ -64,9 +285,21 @@ impl Default for ProcessConfig {
             cmd: None,
             cwd: None,
             env: HashMap::new(),
+            file_env: HashMap::new(),
+            clean_env: false,
             autostart: true,
-            autorestart: false,
+            restart: RestartConfig::default(),
             port: None,
+            depends_on: Vec::new(),
+            ready: None,
+            healthcheck: None,
+            stop_signals: default_stop_signals(),
+            stop_timeout: default_stop_timeout(),
+            shutdown_priority: 0,
+            log: None,
+            watch: None,
+            group: None,
+            raw: None,
         }
     }
 }
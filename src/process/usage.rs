@@ -0,0 +1,142 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::system::procfs;
+
+/// Samples kept for the sparklines (two minutes at the default interval)
+pub const HISTORY_LEN: usize = 120;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// CPU and memory of a whole process group.
+#[derive(Debug, Default)]
+pub struct ResourceUsage {
+    /// Percent of one core; can exceed 100 for multithreaded groups
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub peak_rss_bytes: u64,
+    /// Oldest first, CPU in whole percent
+    pub cpu_history: VecDeque<u64>,
+    /// Oldest first, RSS in bytes
+    pub rss_history: VecDeque<u64>,
+}
+
+impl ResourceUsage {
+    pub fn record(&mut self, cpu_percent: f64, rss_bytes: u64) {
+        self.cpu_percent = cpu_percent;
+        self.rss_bytes = rss_bytes;
+        self.peak_rss_bytes = self.peak_rss_bytes.max(rss_bytes);
+        push_capped(&mut self.cpu_history, cpu_percent.round() as u64);
+        push_capped(&mut self.rss_history, rss_bytes);
+    }
+
+    /// The group is gone: zero the current values but keep the history.
+    pub fn clear_current(&mut self) {
+        self.cpu_percent = 0.0;
+        self.rss_bytes = 0;
+    }
+}
+
+fn push_capped(history: &mut VecDeque<u64>, value: u64) {
+    if history.len() == HISTORY_LEN {
+        history.pop_front();
+    }
+    history.push_back(value);
+}
+
+/// Periodically reads /proc and aggregates usage per process group.
+pub struct UsageSampler {
+    last_sample: Option<Instant>,
+    /// CPU ticks per member pid at the last sample
+    prev_ticks: HashMap<u32, u64>,
+    ticks_per_sec: u64,
+}
+
+impl UsageSampler {
+    pub fn new() -> Self {
+        Self {
+            last_sample: None,
+            prev_ticks: HashMap::new(),
+            ticks_per_sec: procfs::clock_ticks_per_sec(),
+        }
+    }
+
+    pub fn due(&self) -> bool {
+        self.last_sample
+            .is_none_or(|at| at.elapsed() >= SAMPLE_INTERVAL)
+    }
+
+    /// Sample the given process groups. Returns (cpu %, rss bytes) per pgid;
+    /// the first sample reports 0% CPU since there is nothing to compare with.
+    pub fn sample(&mut self, pgids: &[u32]) -> HashMap<u32, (f64, u64)> {
+        let now = Instant::now();
+        let elapsed = self.last_sample.map(|at| now.duration_since(at).as_secs_f64());
+        self.last_sample = Some(now);
+
+        let wanted: HashSet<u32> = pgids.iter().copied().collect();
+        let mut ticks = HashMap::new();
+        let mut totals: HashMap<u32, (u64, u64)> = HashMap::new();
+
+        for stat in procfs::all_processes() {
+            if !wanted.contains(&stat.pgid) {
+                continue;
+            }
+            // A member we haven't seen before appeared since the last sample,
+            // so all of its CPU time falls within this interval
+            let delta = match self.prev_ticks.get(&stat.pid) {
+                Some(&prev) => stat.cpu_ticks.saturating_sub(prev),
+                None if elapsed.is_some() => stat.cpu_ticks,
+                None => 0,
+            };
+            ticks.insert(stat.pid, stat.cpu_ticks);
+
+            let entry = totals.entry(stat.pgid).or_default();
+            entry.0 += delta;
+            entry.1 += procfs::read_rss_bytes(stat.pid).unwrap_or(0);
+        }
+        self.prev_ticks = ticks;
+
+        totals
+            .into_iter()
+            .map(|(pgid, (delta, rss))| {
+                let cpu = match elapsed {
+                    Some(secs) if secs > 0.0 => {
+                        delta as f64 / self.ticks_per_sec as f64 / secs * 100.0
+                    }
+                    _ => 0.0,
+                };
+                (pgid, (cpu, rss))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut usage = ResourceUsage::default();
+        for i in 0..HISTORY_LEN as u64 + 5 {
+            usage.record(i as f64, i * 1024);
+        }
+        assert_eq!(usage.cpu_history.len(), HISTORY_LEN);
+        assert_eq!(usage.cpu_history.front(), Some(&5));
+        assert_eq!(usage.peak_rss_bytes, (HISTORY_LEN as u64 + 4) * 1024);
+
+        usage.clear_current();
+        assert_eq!(usage.rss_bytes, 0);
+        assert_eq!(usage.rss_history.len(), HISTORY_LEN);
+    }
+
+    #[test]
+    fn test_sample_own_group() {
+        let pgid = procfs::read_stat(std::process::id()).unwrap().pgid;
+        let mut sampler = UsageSampler::new();
+        let samples = sampler.sample(&[pgid]);
+        let (cpu, rss) = samples[&pgid];
+        assert_eq!(cpu, 0.0);
+        assert!(rss > 0);
+    }
+}
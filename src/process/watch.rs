@@ -0,0 +1,381 @@
+use anyhow::{bail, Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use crate::config::duration;
+
+/// `watch` as written in YAML: a list of glob patterns, or a settings map.
+#[derive(Debug, Clone, JsonSchema)]
+#[schemars(untagged)]
+pub enum WatchOption {
+    Paths(Vec<String>),
+    Settings(WatchConfig),
+}
+
+impl<'de> Deserialize<'de> for WatchOption {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct WatchVisitor;
+
+        impl<'de> Visitor<'de> for WatchVisitor {
+            type Value = WatchOption;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of patterns or watch settings")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                seq: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                Vec::deserialize(SeqAccessDeserializer::new(seq)).map(WatchOption::Paths)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                WatchConfig::deserialize(MapAccessDeserializer::new(map)).map(WatchOption::Settings)
+            }
+        }
+
+        deserializer.deserialize_any(WatchVisitor)
+    }
+}
+
+impl WatchOption {
+    pub fn into_config(self) -> WatchConfig {
+        match self {
+            WatchOption::Paths(paths) => WatchConfig {
+                paths,
+                ignore: Vec::new(),
+                debounce: default_debounce(),
+            },
+            WatchOption::Settings(config) => config,
+        }
+    }
+}
+
+/// Restart the process when files matching `paths` (and not `ignore`)
+/// change. Patterns are relative to the process cwd; `*` stays within a
+/// directory and `**` matches any number of them. Files under `.git` and
+/// `.betterprocs` (where log files go) are always ignored.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WatchConfig {
+    pub paths: Vec<String>,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Wait for changes to stop for this long before restarting
+    #[serde(default = "default_debounce", with = "duration")]
+    #[schemars(schema_with = "duration::schema")]
+    pub debounce: Duration,
+}
+
+/// Directories that are never watched: git's, and betterprocs' own, which
+/// holds the log files a process would otherwise restart itself with.
+const IGNORED_DIRS: [&str; 2] = [".git", ".betterprocs"];
+
+fn default_debounce() -> Duration {
+    Duration::from_millis(500)
+}
+
+impl WatchConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.paths.is_empty() {
+            bail!("`paths` needs at least one pattern");
+        }
+        glob_set(&self.paths)?;
+        glob_set(&self.ignore)?;
+        Ok(())
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid pattern \"{}\"", pattern))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+/// Watches the files of one process and reports a change once they have
+/// been quiet for the debounce time. Directory trees are watched one
+/// directory at a time, so that ignored ones like `node_modules/**` don't
+/// use up watches.
+pub struct FileWatcher {
+    root: PathBuf,
+    include: GlobSet,
+    ignore: GlobSet,
+    /// Directories `ignore` covers entirely (`dir/**`), not descended into
+    ignore_dirs: GlobSet,
+    /// Directories watched with everything below them, relative to `root`
+    recursive: Vec<PathBuf>,
+    debounce: Duration,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// First file changed since the last report, and when the latest change came
+    pending: Option<(PathBuf, Instant)>,
+    watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    pub fn new(config: &WatchConfig, cwd: Option<&Path>) -> Result<Self> {
+        let root = cwd.unwrap_or(Path::new("."));
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", root.display()))?;
+        let (tx, events) = channel();
+        let watcher = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+        let ignore_dirs: Vec<String> = config
+            .ignore
+            .iter()
+            .filter_map(|p| p.strip_suffix("/**"))
+            .map(String::from)
+            .collect();
+        let mut this = Self {
+            root,
+            include: glob_set(&config.paths)?,
+            ignore: glob_set(&config.ignore)?,
+            ignore_dirs: glob_set(&ignore_dirs)?,
+            recursive: Vec::new(),
+            debounce: config.debounce,
+            events,
+            pending: None,
+            watcher,
+        };
+
+        let mut watched = 0;
+        for (dir, mode) in watch_dirs(&config.paths) {
+            let path = this.root.join(&dir);
+            if !path.is_dir() {
+                continue;
+            }
+            match mode {
+                RecursiveMode::Recursive => {
+                    this.watch_tree(&path)?;
+                    this.recursive.push(dir);
+                }
+                RecursiveMode::NonRecursive => this.watch_dir(&path)?,
+            }
+            watched += 1;
+        }
+        if watched == 0 {
+            bail!("No directory to watch under {}", this.root.display());
+        }
+        Ok(this)
+    }
+
+    fn watch_dir(&mut self, dir: &Path) -> Result<()> {
+        self.watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", dir.display()))
+    }
+
+    /// Watch a directory and the ones below it, skipping ignored ones.
+    fn watch_tree(&mut self, dir: &Path) -> Result<()> {
+        self.watch_dir(dir)?;
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Ok(());
+        };
+        for entry in entries.flatten() {
+            // Symlinks are not followed, so there are no loops
+            if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                continue;
+            }
+            let path = entry.path();
+            let skipped = path
+                .strip_prefix(&self.root)
+                .is_ok_and(|relative| skipped_dir(relative, &self.ignore_dirs));
+            if !skipped {
+                self.watch_tree(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The first file that changed (relative to the cwd), once changes
+    /// have settled.
+    pub fn poll(&mut self) -> Option<PathBuf> {
+        while let Ok(event) = self.events.try_recv() {
+            let Ok(event) = event else { continue };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in &event.paths {
+                let Ok(relative) = path.strip_prefix(&self.root) else {
+                    continue;
+                };
+                if in_ignored_dir(relative) {
+                    continue;
+                }
+                let relative = relative.to_path_buf();
+                if matches!(event.kind, EventKind::Create(_))
+                    && path.is_dir()
+                    && self.recursive.iter().any(|dir| relative.starts_with(dir))
+                    && !skipped_dir(&relative, &self.ignore_dirs)
+                {
+                    // A new directory in a watched tree; it may be gone again
+                    let _ = self.watch_tree(path);
+                }
+                if self.include.is_match(&relative) && !self.ignore.is_match(&relative) {
+                    let first = match self.pending.take() {
+                        Some((first, _)) => first,
+                        None => relative,
+                    };
+                    self.pending = Some((first, Instant::now()));
+                }
+            }
+        }
+
+        match self.pending {
+            Some((_, last)) if last.elapsed() >= self.debounce => self.pending.take().map(|(p, _)| p),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a path is under `.git` or `.betterprocs`.
+fn in_ignored_dir(relative: &Path) -> bool {
+    relative
+        .components()
+        .any(|c| IGNORED_DIRS.iter().any(|dir| c.as_os_str() == *dir))
+}
+
+/// Whether a directory and everything in it is ignored.
+fn skipped_dir(relative: &Path, ignore_dirs: &GlobSet) -> bool {
+    in_ignored_dir(relative) || ignore_dirs.is_match(relative)
+}
+
+/// Directories to watch for the patterns: the literal leading directories
+/// of each, recursively if the rest of the pattern spans directories.
+fn watch_dirs(patterns: &[String]) -> Vec<(PathBuf, RecursiveMode)> {
+    let mut dirs: Vec<(PathBuf, RecursiveMode)> = Vec::new();
+    for pattern in patterns {
+        let parts: Vec<&str> = pattern.split('/').collect();
+        let literal = parts[..parts.len() - 1]
+            .iter()
+            .take_while(|part| !part.contains(['*', '?', '[', '{']))
+            .count();
+        let dir: PathBuf = parts[..literal].iter().collect();
+        let mode = if parts.len() - literal > 1 || parts[literal].contains("**") {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        match dirs.iter_mut().find(|(d, _)| *d == dir) {
+            Some(existing) if mode == RecursiveMode::Recursive => existing.1 = mode,
+            Some(_) => {}
+            None => dirs.push((dir, mode)),
+        }
+    }
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_dirs() {
+        let patterns = [
+            "src/**/*.rs".to_string(),
+            "Cargo.toml".to_string(),
+            "*.json".to_string(),
+            "config/app/*.yaml".to_string(),
+            "src/main.rs".to_string(),
+        ];
+        assert_eq!(
+            watch_dirs(&patterns),
+            [
+                (PathBuf::from("src"), RecursiveMode::Recursive),
+                (PathBuf::new(), RecursiveMode::NonRecursive),
+                (PathBuf::from("config/app"), RecursiveMode::NonRecursive),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_patterns() {
+        let include = glob_set(&["src/**/*.rs".to_string(), "*.toml".to_string()]).unwrap();
+        assert!(include.is_match("src/main.rs"));
+        assert!(include.is_match("src/a/b/lib.rs"));
+        assert!(include.is_match("Cargo.toml"));
+        assert!(!include.is_match("crates/x/Cargo.toml"));
+
+        let config = WatchConfig {
+            paths: vec!["src/[".to_string()],
+            ignore: Vec::new(),
+            debounce: default_debounce(),
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_skipped_dirs() {
+        let ignore_dirs = glob_set(&["**/node_modules".to_string(), "target".to_string()]).unwrap();
+        assert!(skipped_dir(Path::new(".git"), &ignore_dirs));
+        assert!(skipped_dir(Path::new("web/.betterprocs/logs"), &ignore_dirs));
+        assert!(skipped_dir(Path::new("web/node_modules"), &ignore_dirs));
+        assert!(skipped_dir(Path::new("target"), &ignore_dirs));
+        assert!(!skipped_dir(Path::new("src/target"), &ignore_dirs));
+        assert!(!skipped_dir(Path::new("src/.github"), &ignore_dirs));
+    }
+
+    #[test]
+    fn test_ignores_log_files() {
+        let dir = std::env::temp_dir().join(format!("betterprocs-watch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join(".betterprocs/logs")).unwrap();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let config = WatchConfig {
+            paths: vec!["**".to_string()],
+            ignore: Vec::new(),
+            debounce: Duration::from_millis(10),
+        };
+        let mut watcher = FileWatcher::new(&config, Some(&dir)).unwrap();
+
+        let settle = |watcher: &mut FileWatcher| {
+            std::thread::sleep(Duration::from_millis(200));
+            watcher.poll();
+            std::thread::sleep(Duration::from_millis(20));
+            watcher.poll()
+        };
+        std::fs::write(dir.join(".betterprocs/logs/web.log"), "output").unwrap();
+        assert_eq!(settle(&mut watcher), None);
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        assert_eq!(settle(&mut watcher), Some(PathBuf::from("src/main.rs")));
+
+        // Directories created later are watched too
+        std::fs::create_dir(dir.join("src/api")).unwrap();
+        settle(&mut watcher);
+        std::fs::write(dir.join("src/api/mod.rs"), "").unwrap();
+        assert_eq!(settle(&mut watcher), Some(PathBuf::from("src/api/mod.rs")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_yaml_forms() {
+        let list: WatchOption = serde_yaml::from_str("[\"src/**\"]").unwrap();
+        assert_eq!(list.into_config().debounce, Duration::from_millis(500));
+        let map: WatchOption =
+            serde_yaml::from_str("paths: [\"**/*.py\"]\nignore: [\"**/__pycache__/**\"]\ndebounce: 1s")
+                .unwrap();
+        let config = map.into_config();
+        assert_eq!(config.ignore, ["**/__pycache__/**"]);
+        assert_eq!(config.debounce, Duration::from_secs(1));
+    }
+}
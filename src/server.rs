@@ -0,0 +1,217 @@
+use anyhow::{bail, Context, Result};
+use crossterm::event::Event;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::Rect;
+use ratatui::{Terminal, TerminalOptions, Viewport};
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::mpsc;
+
+use crate::app::App;
+use crate::config::merged::AppConfig;
+use crate::control;
+use crate::control::server::{Attachment, ControlServer};
+use crate::tui::renderer::render;
+
+/// Where a background server writes its own errors
+const SERVER_LOG: &str = ".betterprocs/server.log";
+
+/// Run as a background server: own the processes and draw the TUI for
+/// whichever client is attached. Detaching leaves everything running;
+/// quitting from a client (or SIGTERM) stops the processes and the server.
+pub async fn run(config: AppConfig) -> Result<()> {
+    let dir = std::env::current_dir().context("Failed to get current directory")?;
+    let mut app = App::new(config);
+    app.server_mode = true;
+    app.control = Some(ControlServer::bind(&dir, true)?);
+
+    let mut client: Option<Client> = None;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+    // No controlling terminal, but ignore a stray hangup all the same
+    let mut sighup = signal(SignalKind::hangup())?;
+
+    loop {
+        app.tick();
+
+        let attachment = app.control.as_mut().and_then(|c| c.take_attachment());
+        if let Some(attachment) = attachment {
+            // A new client takes over from the current one
+            client = Some(Client::attach(attachment, &mut app)?);
+        }
+
+        if let Some(ref mut c) = client {
+            let connected = c.handle_events(&mut app);
+            c.sync_size(&app);
+            if !connected || app.detach_requested || !c.draw(&mut app) {
+                client = None;
+            }
+        }
+        app.detach_requested = false;
+
+        if app.advance_quit() {
+            // Take the socket down first, so the client sees this as a quit
+            app.control = None;
+            break;
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(50)) => {}
+            _ = sigint.recv() => app.handle_event(&quit_key()),
+            _ = sigterm.recv() => app.handle_event(&quit_key()),
+            _ = sighup.recv() => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Same as pressing q: stop gracefully the first time, force kill the next.
+fn quit_key() -> Event {
+    Event::Key(crossterm::event::KeyEvent::from(crossterm::event::KeyCode::Char('q')))
+}
+
+/// The attached client's terminal, drawn into the connection.
+struct Client {
+    terminal: Terminal<CrosstermBackend<ClientWriter>>,
+    events: mpsc::UnboundedReceiver<Event>,
+}
+
+impl Client {
+    fn attach(attachment: Attachment, app: &mut App) -> Result<Self> {
+        let Attachment {
+            cols,
+            rows,
+            events,
+            output,
+        } = attachment;
+        let backend = CrosstermBackend::new(ClientWriter {
+            buf: Vec::new(),
+            output,
+        });
+        let mut terminal = Terminal::with_options(
+            backend,
+            TerminalOptions {
+                viewport: Viewport::Fixed(Rect::new(0, 0, cols, rows)),
+            },
+        )?;
+        // Everything is redrawn from the server's screens, scrollback included
+        terminal.clear()?;
+        app.handle_event(&Event::Resize(cols, rows));
+        Ok(Self { terminal, events })
+    }
+
+    /// Draw a frame. False once the client has gone away.
+    fn draw(&mut self, app: &mut App) -> bool {
+        self.terminal.draw(|frame| render(frame, app)).is_ok()
+    }
+
+    /// Feed the client's input to the app. False once the client has gone away.
+    fn handle_events(&mut self, app: &mut App) -> bool {
+        loop {
+            match self.events.try_recv() {
+                Ok(event) => app.handle_event(&event),
+                Err(mpsc::error::TryRecvError::Empty) => return true,
+                Err(mpsc::error::TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// Follow the client's terminal size (the app tracks resize events).
+    fn sync_size(&mut self, app: &App) {
+        let (cols, rows) = app.term_size;
+        let area = self.terminal.get_frame().area();
+        if (area.width, area.height) != (cols, rows) {
+            let _ = self.terminal.resize(Rect::new(0, 0, cols, rows));
+        }
+    }
+}
+
+/// Buffers a frame's escape sequences and hands them to the connection
+/// on flush.
+struct ClientWriter {
+    buf: Vec<u8>,
+    output: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+impl Write for ClientWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        self.output
+            .send(std::mem::take(&mut self.buf))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+}
+
+/// Start a background server for this directory with the same arguments
+/// (minus `--daemon`) and wait until it is accepting connections.
+pub fn spawn_daemon(dir: &Path) -> Result<()> {
+    let socket = control::socket_path(dir);
+    if std::os::unix::net::UnixStream::connect(&socket).is_ok() {
+        bail!(
+            "betterprocs is already running in {}; use `betterprocs attach`",
+            dir.display()
+        );
+    }
+
+    let log_path = dir.join(SERVER_LOG);
+    if let Some(parent) = log_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let log = std::fs::File::create(&log_path)
+        .with_context(|| format!("Failed to create {}", log_path.display()))?;
+
+    let exe = std::env::current_exe().context("Failed to find the betterprocs executable")?;
+    let args: Vec<OsString> = std::env::args_os()
+        .skip(1)
+        .filter(|a| a != "--daemon")
+        .collect();
+    let mut command = std::process::Command::new(exe);
+    command
+        .arg("--serve")
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log);
+    // Own session: closing this terminal must not take the server with it
+    // SAFETY: the closure runs between fork and exec and only calls setsid,
+    // which is async-signal-safe
+    unsafe {
+        command.pre_exec(|| {
+            nix::unistd::setsid().map_err(io::Error::from)?;
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().context("Failed to start the server")?;
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if std::os::unix::net::UnixStream::connect(&socket).is_ok() {
+            return Ok(());
+        }
+        if let Some(status) = child.try_wait()? {
+            let log = std::fs::read_to_string(&log_path).unwrap_or_default();
+            bail!("Server exited ({}):\n{}", status, log.trim_end());
+        }
+        if Instant::now() >= deadline {
+            bail!(
+                "Server didn't start within 10s, see {}",
+                log_path.display()
+            );
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
@@ -91,7 +91,7 @@ impl PortKiller {
 fn scan_listening_ports(filter: &str) -> Vec<PortEntry> {
     // Parse filter into specific port numbers
     let filter_ports: Vec<u16> = filter
-        .split(|c: char| c == ',' || c == ' ')
+        .split([',', ' '])
         .filter_map(|s| s.trim().parse::<u16>().ok())
         .collect();
 
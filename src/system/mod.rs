@@ -1,2 +1,4 @@
 pub mod browser;
 pub mod killer;
+pub mod procfs;
+pub mod tree;
@@ -0,0 +1,117 @@
+use std::fs;
+
+/// One process as seen in /proc/<pid>/stat.
+#[derive(Debug, Clone)]
+pub struct ProcStat {
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgid: u32,
+    pub comm: String,
+    /// utime + stime, in clock ticks
+    pub cpu_ticks: u64,
+}
+
+/// Read /proc/<pid>/stat. None if the process is gone or unreadable.
+pub fn read_stat(pid: u32) -> Option<ProcStat> {
+    let contents = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    parse_stat(&contents)
+}
+
+/// Resident set size from the VmRSS line of /proc/<pid>/status, in bytes.
+/// Kernel threads and zombies have no VmRSS.
+pub fn read_rss_bytes(pid: u32) -> Option<u64> {
+    let contents = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    parse_vm_rss(&contents)
+}
+
+/// Full command line from /proc/<pid>/cmdline, arguments joined by spaces.
+/// Empty for kernel threads and zombies.
+pub fn read_cmdline(pid: u32) -> Option<String> {
+    let raw = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let args: Vec<String> = raw
+        .split(|&b| b == 0)
+        .filter(|a| !a.is_empty())
+        .map(|a| String::from_utf8_lossy(a).into_owned())
+        .collect();
+    Some(args.join(" "))
+}
+
+/// Every process currently visible in /proc.
+pub fn all_processes() -> Vec<ProcStat> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(read_stat)
+        .collect()
+}
+
+/// Clock ticks per second, the unit of the CPU times in /proc/<pid>/stat.
+pub fn clock_ticks_per_sec() -> u64 {
+    // SAFETY: sysconf has no preconditions
+    let ticks = unsafe { nix::libc::sysconf(nix::libc::_SC_CLK_TCK) };
+    if ticks > 0 {
+        ticks as u64
+    } else {
+        100
+    }
+}
+
+fn parse_stat(contents: &str) -> Option<ProcStat> {
+    // "1234 (some name) S 1 1234 ..." — the name may contain spaces and parens,
+    // so split on the last ')'
+    let open = contents.find('(')?;
+    let close = contents.rfind(')')?;
+    let pid = contents[..open].trim().parse().ok()?;
+    let comm = contents[open + 1..close].to_string();
+
+    // Fields after the name, starting with field 3 (state)
+    let fields: Vec<&str> = contents[close + 1..].split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+
+    Some(ProcStat {
+        pid,
+        ppid: field(4)? as u32,
+        pgid: field(5)? as u32,
+        comm,
+        cpu_ticks: field(14)? + field(15)?,
+    })
+}
+
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        let line = "4242 (node (dev)) S 4200 4242 4242 34816 4242 4194304 1530 0 0 0 250 40 0 0 20 0 11 0 123 0 0";
+        let stat = parse_stat(line).unwrap();
+        assert_eq!(stat.pid, 4242);
+        assert_eq!(stat.comm, "node (dev)");
+        assert_eq!(stat.ppid, 4200);
+        assert_eq!(stat.pgid, 4242);
+        assert_eq!(stat.cpu_ticks, 290);
+    }
+
+    #[test]
+    fn test_parse_vm_rss() {
+        let status = "Name:\tnode\nVmPeak:\t  900000 kB\nVmRSS:\t  204800 kB\n";
+        assert_eq!(parse_vm_rss(status), Some(204800 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tkthreadd\n"), None);
+    }
+
+    #[test]
+    fn test_read_own_stat() {
+        let stat = read_stat(std::process::id()).unwrap();
+        assert_eq!(stat.pid, std::process::id());
+        assert!(read_rss_bytes(std::process::id()).unwrap() > 0);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use super::procfs::{self, ProcStat};
+
+pub struct TreeEntry {
+    pub pid: u32,
+    /// Nesting below the managed process (0 for the process itself)
+    pub depth: usize,
+    pub cmdline: String,
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+}
+
+/// Live view of everything a managed process has spawned.
+pub struct ProcessTree {
+    pub selected: usize,
+    root: Option<u32>,
+    entries: Vec<TreeEntry>,
+    last_refresh: Instant,
+    /// CPU ticks per pid at the last refresh, for CPU %
+    prev_ticks: HashMap<u32, u64>,
+    ticks_per_sec: u64,
+}
+
+impl ProcessTree {
+    pub fn new() -> Self {
+        Self {
+            selected: 0,
+            root: None,
+            entries: Vec::new(),
+            last_refresh: Instant::now(),
+            prev_ticks: HashMap::new(),
+            ticks_per_sec: procfs::clock_ticks_per_sec(),
+        }
+    }
+
+    /// Show the tree below `root` (the pid of a managed process), refreshing
+    /// right away when it changes.
+    pub fn set_root(&mut self, root: Option<u32>) {
+        if self.root != root {
+            self.root = root;
+            self.selected = 0;
+            self.prev_ticks.clear();
+            self.refresh();
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        let elapsed = self.last_refresh.elapsed().as_secs_f64();
+        self.last_refresh = Instant::now();
+
+        let stats = match self.root {
+            Some(root) => collect_tree(root, procfs::all_processes()),
+            None => Vec::new(),
+        };
+
+        let mut ticks = HashMap::new();
+        self.entries = stats
+            .into_iter()
+            .map(|(depth, stat)| {
+                let cpu_percent = match self.prev_ticks.get(&stat.pid) {
+                    Some(&prev) if elapsed > 0.0 => {
+                        stat.cpu_ticks.saturating_sub(prev) as f64
+                            / self.ticks_per_sec as f64
+                            / elapsed
+                            * 100.0
+                    }
+                    _ => 0.0,
+                };
+                ticks.insert(stat.pid, stat.cpu_ticks);
+                let cmdline = procfs::read_cmdline(stat.pid)
+                    .filter(|c| !c.is_empty())
+                    .unwrap_or_else(|| format!("[{}]", stat.comm));
+                TreeEntry {
+                    pid: stat.pid,
+                    depth,
+                    cmdline,
+                    cpu_percent,
+                    rss_bytes: procfs::read_rss_bytes(stat.pid).unwrap_or(0),
+                }
+            })
+            .collect();
+        self.prev_ticks = ticks;
+
+        if self.selected >= self.entries.len() && !self.entries.is_empty() {
+            self.selected = self.entries.len() - 1;
+        }
+    }
+
+    pub fn maybe_refresh(&mut self) {
+        if self.last_refresh.elapsed().as_secs() >= 1 {
+            self.refresh();
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + 1) % self.entries.len();
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        if !self.entries.is_empty() {
+            if self.selected == 0 {
+                self.selected = self.entries.len() - 1;
+            } else {
+                self.selected -= 1;
+            }
+        }
+    }
+
+    pub fn selected_pid(&self) -> Option<u32> {
+        self.entries.get(self.selected).map(|e| e.pid)
+    }
+
+    pub fn entries(&self) -> &[TreeEntry] {
+        &self.entries
+    }
+}
+
+/// Depth-first walk of the descendants of `root`, children in pid order.
+/// Members of root's process group that were reparented (e.g. after their
+/// parent exited) are listed at the end at depth 1, since a group stop
+/// still reaches them.
+fn collect_tree(root: u32, all: Vec<ProcStat>) -> Vec<(usize, ProcStat)> {
+    let mut children: HashMap<u32, Vec<ProcStat>> = HashMap::new();
+    let mut root_stat = None;
+    for stat in all {
+        if stat.pid == root {
+            root_stat = Some(stat);
+        } else {
+            children.entry(stat.ppid).or_default().push(stat);
+        }
+    }
+    for list in children.values_mut() {
+        list.sort_by_key(|s| s.pid);
+    }
+
+    let mut out = Vec::new();
+    let mut stack: Vec<(usize, ProcStat)> = match root_stat {
+        Some(stat) => vec![(0, stat)],
+        None => Vec::new(),
+    };
+    while let Some((depth, stat)) = stack.pop() {
+        if let Some(kids) = children.remove(&stat.pid) {
+            stack.extend(kids.into_iter().rev().map(|k| (depth + 1, k)));
+        }
+        out.push((depth, stat));
+    }
+
+    let mut strays: Vec<ProcStat> = children
+        .into_values()
+        .flatten()
+        .filter(|s| s.pgid == root)
+        .collect();
+    strays.sort_by_key(|s| s.pid);
+    out.extend(strays.into_iter().map(|s| (1, s)));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(pid: u32, ppid: u32, pgid: u32) -> ProcStat {
+        ProcStat {
+            pid,
+            ppid,
+            pgid,
+            comm: format!("p{}", pid),
+            cpu_ticks: 0,
+        }
+    }
+
+    #[test]
+    fn test_collect_tree() {
+        let all = vec![
+            stat(1, 0, 1),
+            stat(100, 1, 100),
+            stat(103, 101, 100),
+            stat(101, 100, 100),
+            stat(102, 100, 100),
+            stat(104, 1, 100), // orphaned group member
+            stat(200, 1, 200),
+        ];
+        let tree: Vec<(usize, u32)> = collect_tree(100, all)
+            .into_iter()
+            .map(|(d, s)| (d, s.pid))
+            .collect();
+        assert_eq!(tree, vec![(0, 100), (1, 101), (2, 103), (1, 102), (1, 104)]);
+    }
+
+    #[test]
+    fn test_missing_root() {
+        assert!(collect_tree(100, vec![stat(1, 0, 1)]).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+/// Splits raw PTY output into plain-text lines, dropping ANSI escape sequences.
+/// Bytes may arrive in arbitrary chunks; incomplete lines and escape
+/// sequences are carried over to the next `push`.
+pub struct LineBuffer {
+    partial: Vec<u8>,
+    state: EscapeState,
+    carriage_return: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EscapeState {
+    Text,
+    /// Just saw ESC
+    Escape,
+    /// Inside `ESC [ ...` until a final byte
+    Csi,
+    /// Inside `ESC ] ...` until BEL or `ESC \`
+    Osc,
+    /// Saw ESC inside an OSC string (possible `ESC \` terminator)
+    OscEscape,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self {
+            partial: Vec::new(),
+            state: EscapeState::Text,
+            carriage_return: false,
+        }
+    }
+
+    /// Feed bytes and return every line completed by them (without the newline).
+    pub fn push(&mut self, data: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for &b in data {
+            match self.state {
+                EscapeState::Escape => {
+                    self.state = match b {
+                        b'[' => EscapeState::Csi,
+                        b']' => EscapeState::Osc,
+                        _ => EscapeState::Text,
+                    };
+                }
+                EscapeState::Csi => {
+                    if (0x40..=0x7e).contains(&b) {
+                        self.state = EscapeState::Text;
+                    }
+                }
+                EscapeState::Osc => match b {
+                    0x07 => self.state = EscapeState::Text,
+                    0x1b => self.state = EscapeState::OscEscape,
+                    _ => {}
+                },
+                EscapeState::OscEscape => {
+                    self.state = if b == b'\\' {
+                        EscapeState::Text
+                    } else {
+                        EscapeState::Osc
+                    };
+                }
+                EscapeState::Text => match b {
+                    0x1b => self.state = EscapeState::Escape,
+                    b'\n' => {
+                        lines.push(String::from_utf8_lossy(&self.partial).into_owned());
+                        self.partial.clear();
+                        self.carriage_return = false;
+                    }
+                    b'\r' => self.carriage_return = true,
+                    b'\t' => self.push_text(b),
+                    b if b < 0x20 || b == 0x7f => {}
+                    b => self.push_text(b),
+                },
+            }
+        }
+
+        lines
+    }
+
+    /// The current, not yet terminated line.
+    pub fn partial(&self) -> String {
+        String::from_utf8_lossy(&self.partial).into_owned()
+    }
+
+    fn push_text(&mut self, b: u8) {
+        // A bare carriage return followed by text overwrites the line (progress bars)
+        if self.carriage_return {
+            self.partial.clear();
+            self.carriage_return = false;
+        }
+        self.partial.push(b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_colors_and_splits_lines() {
+        let mut buf = LineBuffer::new();
+        let lines = buf.push(b"\x1b[32mready\x1b[0m on :3000\r\nnext");
+        assert_eq!(lines, vec!["ready on :3000"]);
+        assert_eq!(buf.partial(), "next");
+    }
+
+    #[test]
+    fn test_split_across_chunks() {
+        let mut buf = LineBuffer::new();
+        assert!(buf.push(b"hel\x1b[1").is_empty());
+        assert_eq!(buf.push(b";31mlo\n"), vec!["hello"]);
+    }
+
+    #[test]
+    fn test_carriage_return_overwrites() {
+        let mut buf = LineBuffer::new();
+        assert_eq!(buf.push(b"10%\r50%\r100%\n"), vec!["100%"]);
+    }
+
+    #[test]
+    fn test_osc_title_is_dropped() {
+        let mut buf = LineBuffer::new();
+        assert_eq!(buf.push(b"\x1b]0;title\x07done\n"), vec!["done"]);
+    }
+}
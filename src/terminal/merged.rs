@@ -0,0 +1,123 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::SystemTime;
+
+/// One output line of one process, for the merged view.
+pub struct MergedLine {
+    pub process: String,
+    pub time: SystemTime,
+    pub text: String,
+}
+
+/// Interleaved plain-text output of every process, in arrival order.
+pub struct MergedLog {
+    lines: VecDeque<MergedLine>,
+    capacity: usize,
+    /// Lines pushed since creation, including ones already dropped
+    pushed: u64,
+}
+
+impl MergedLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            capacity,
+            pushed: 0,
+        }
+    }
+
+    pub fn push(&mut self, process: &str, time: SystemTime, text: String) {
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(MergedLine {
+            process: process.to_string(),
+            time,
+            text,
+        });
+        self.pushed += 1;
+    }
+
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    /// Lines pushed after the first `seen` ones that are still retained.
+    pub fn since(&self, seen: u64) -> impl Iterator<Item = &MergedLine> {
+        let new = self.pushed.saturating_sub(seen).min(self.lines.len() as u64) as usize;
+        self.lines.iter().skip(self.lines.len() - new)
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Pick a stable palette index for a process name.
+    pub fn color_index(name: &str, palette_len: usize) -> usize {
+        let hash = name
+            .bytes()
+            .fold(0usize, |h, b| h.wrapping_mul(31).wrapping_add(b as usize));
+        hash % palette_len
+    }
+
+    /// The `height` lines to show, oldest first, skipping processes in
+    /// `hidden` and ending `scroll` lines above the newest one.
+    pub fn window(&self, hidden: &HashSet<String>, height: usize, scroll: usize) -> Vec<&MergedLine> {
+        let mut window: Vec<&MergedLine> = self
+            .lines
+            .iter()
+            .rev()
+            .filter(|l| !hidden.contains(&l.process))
+            .skip(scroll)
+            .take(height)
+            .collect();
+        window.reverse();
+        window
+    }
+
+    /// Lines shown with this filter; bounds how far the view can scroll.
+    pub fn visible_count(&self, hidden: &HashSet<String>) -> usize {
+        self.lines
+            .iter()
+            .filter(|l| !hidden.contains(&l.process))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(lines: Vec<&MergedLine>) -> Vec<&str> {
+        lines.into_iter().map(|l| l.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_window_filters_and_scrolls() {
+        let mut log = MergedLog::new(100);
+        let now = SystemTime::now();
+        for i in 0..6 {
+            let process = if i % 2 == 0 { "api" } else { "web" };
+            log.push(process, now, format!("{}{}", process, i));
+        }
+
+        let none = HashSet::new();
+        assert_eq!(texts(log.window(&none, 2, 0)), vec!["api4", "web5"]);
+        assert_eq!(texts(log.window(&none, 2, 1)), vec!["web3", "api4"]);
+
+        let hidden: HashSet<String> = ["web".to_string()].into();
+        assert_eq!(texts(log.window(&hidden, 10, 0)), vec!["api0", "api2", "api4"]);
+        assert_eq!(log.visible_count(&hidden), 3);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut log = MergedLog::new(3);
+        for i in 0..5 {
+            log.push("api", SystemTime::now(), i.to_string());
+        }
+        assert_eq!(texts(log.window(&HashSet::new(), 10, 0)), vec!["2", "3", "4"]);
+        assert_eq!(texts(log.since(3).collect()), vec!["3", "4"]);
+        assert_eq!(texts(log.since(0).collect()), vec!["2", "3", "4"]);
+        assert_eq!(log.since(5).count(), 0);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod copy_mode;
+pub mod lines;
+pub mod merged;
 pub mod screen;
@@ -1,8 +1,17 @@
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
 pub struct TerminalScreen {
     parser: vt100::Parser,
     pub scroll_offset: usize,
     rows: u16,
     cols: u16,
+    scrollback_len: usize,
+    /// Rows pushed into the scrollback so far; screen row 0 is absolute row `scrolled`
+    scrolled: u64,
+    /// When the line starting on each absolute row arrived, from `first_row` on
+    row_times: VecDeque<Option<SystemTime>>,
+    first_row: u64,
 }
 
 impl TerminalScreen {
@@ -12,11 +21,93 @@ impl TerminalScreen {
             scroll_offset: 0,
             rows,
             cols,
+            scrollback_len: scrollback,
+            scrolled: 0,
+            row_times: VecDeque::new(),
+            first_row: 0,
         }
     }
 
+    /// Feed output, timestamping each line as it starts.
     pub fn process_bytes(&mut self, data: &[u8]) {
-        self.parser.process(data);
+        let now = SystemTime::now();
+        for segment in data.split_inclusive(|&b| b == b'\n') {
+            let (row, col) = self.parser.screen().cursor_position();
+            let abs = self.scrolled + row as u64;
+            // A line starting at column 0 replaces whatever was on the row
+            // before (e.g. after a clear); a continued line keeps its time
+            if col == 0 || self.row_time_abs(abs).is_none() {
+                self.set_row_time(abs, now);
+            }
+            self.scrolled += self.process_counting_scroll(segment);
+        }
+        self.prune_row_times();
+    }
+
+    /// Arrival time of the line starting on a visible row (taking the
+    /// current scroll position into account). None for wrapped continuation
+    /// rows and rows without output.
+    pub fn row_time(&self, visible_row: u16) -> Option<SystemTime> {
+        let top = self.scrolled.checked_sub(self.parser.screen().scrollback() as u64)?;
+        self.row_time_abs(top + visible_row as u64)
+    }
+
+    /// Process bytes and return how many rows scrolled into the scrollback.
+    /// vt100 doesn't report this directly, but it bumps a nonzero scrollback
+    /// offset for every row that scrolls off (so the view stays put), so
+    /// start from offset 1 and see how far it moved.
+    fn process_counting_scroll(&mut self, data: &[u8]) -> u64 {
+        let view = self.parser.screen().scrollback();
+        let len_before = self.scrollback_rows();
+
+        let scrolled = if len_before > 0 {
+            self.parser.screen_mut().set_scrollback(1);
+            self.parser.process(data);
+            self.parser.screen().scrollback().saturating_sub(1)
+        } else {
+            self.parser.process(data);
+            self.scrollback_rows()
+        };
+
+        // Restore the view, following the content like vt100 would have
+        let view = if view > 0 { view + scrolled } else { 0 };
+        self.parser.screen_mut().set_scrollback(view);
+        self.scroll_offset = self.parser.screen().scrollback();
+        scrolled as u64
+    }
+
+    /// Rows currently held in the scrollback buffer.
+    fn scrollback_rows(&mut self) -> usize {
+        let view = self.parser.screen().scrollback();
+        self.parser.screen_mut().set_scrollback(usize::MAX);
+        let rows = self.parser.screen().scrollback();
+        self.parser.screen_mut().set_scrollback(view);
+        rows
+    }
+
+    fn row_time_abs(&self, abs: u64) -> Option<SystemTime> {
+        let idx = abs.checked_sub(self.first_row)?;
+        self.row_times.get(idx as usize).copied().flatten()
+    }
+
+    fn set_row_time(&mut self, abs: u64, time: SystemTime) {
+        if abs < self.first_row {
+            return;
+        }
+        let idx = (abs - self.first_row) as usize;
+        if idx >= self.row_times.len() {
+            self.row_times.resize(idx + 1, None);
+        }
+        self.row_times[idx] = Some(time);
+    }
+
+    /// Forget rows that fell out of the scrollback.
+    fn prune_row_times(&mut self) {
+        let oldest = self.scrolled.saturating_sub(self.scrollback_len as u64);
+        while self.first_row < oldest && !self.row_times.is_empty() {
+            self.row_times.pop_front();
+            self.first_row += 1;
+        }
     }
 
     pub fn screen(&self) -> &vt100::Screen {
@@ -38,8 +129,11 @@ impl TerminalScreen {
     }
 
     pub fn clear(&mut self) {
-        self.parser = vt100::Parser::new(self.rows, self.cols, 10_000);
+        self.parser = vt100::Parser::new(self.rows, self.cols, self.scrollback_len);
         self.scroll_offset = 0;
+        self.scrolled = 0;
+        self.row_times.clear();
+        self.first_row = 0;
     }
 
     pub fn scroll_up(&mut self, n: usize) {
@@ -65,3 +159,43 @@ impl TerminalScreen {
         self.scroll_offset = self.parser.screen().scrollback();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_times_follow_scrolling() {
+        let mut screen = TerminalScreen::new(3, 20, 100);
+        screen.process_bytes(b"one\r\n");
+        let first = screen.row_time(0).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        screen.process_bytes(b"two\r\nthree\r\nfour\r\n");
+        assert_eq!(screen.scrolled, 2);
+
+        // "three" is now at the top of the screen, "one" is in the scrollback
+        assert!(screen.row_time(0).unwrap() > first);
+        screen.scroll_up(2);
+        assert_eq!(screen.row_time(0), Some(first));
+    }
+
+    #[test]
+    fn test_wrapped_rows_have_no_time() {
+        let mut screen = TerminalScreen::new(5, 4, 100);
+        screen.process_bytes(b"abcdefgh\r\nxy\r\n");
+        assert!(screen.row_time(0).is_some());
+        assert!(screen.row_time(1).is_none());
+        assert!(screen.row_time(2).is_some());
+    }
+
+    #[test]
+    fn test_view_stays_put_while_scrolled_up() {
+        let mut screen = TerminalScreen::new(2, 10, 100);
+        screen.process_bytes(b"a\r\nb\r\nc\r\nd\r\n");
+        screen.scroll_up(1);
+        let row = screen.screen().contents();
+        screen.process_bytes(b"e\r\n");
+        assert_eq!(screen.screen().contents(), row);
+        assert_eq!(screen.scroll_offset, 2);
+    }
+}
@@ -12,19 +12,37 @@ pub enum Action {
     ForceKill,
     ClearLogs,
     ToggleKeymap,
+    ToggleDetails,
+    ToggleProcessTree,
+    ToggleTimestamps,
     ScrollUp(u16),
     ScrollDown(u16),
     SwitchToPortKiller,
     SwitchToProcesses,
+    SwitchToAllLogs,
+    ToggleMergedFilter,
+    SoloMergedFilter,
+    ShowAllMerged,
     PortKillerType(char),
     PortKillerBackspace,
     PortKillerClear,
-    SelectIndex(usize),
+    SelectRow(usize),
     ClickOutputPane,
     MouseDragStart(u16, u16),
     MouseDragEnd(u16, u16),
     CopySelection,
     SendInput(Vec<u8>),
     Resize(u16, u16),
+    OpenAddDialog,
+    OpenEditDialog,
+    RemoveProcess,
+    DialogType(char),
+    DialogBackspace,
+    DialogNextField,
+    DialogPrevField,
+    DialogSubmit,
+    DialogCancel,
+    /// Detach the client from a background server
+    Detach,
     None,
 }
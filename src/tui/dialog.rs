@@ -0,0 +1,293 @@
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::config::env::interpolate_config;
+use crate::process::types::{ProcessConfig, RawCommand};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialogMode {
+    Add,
+    /// Editing the process with this name
+    Edit(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DialogField {
+    Name,
+    Command,
+    Cwd,
+    Env,
+    /// Write the change back to the config file
+    Save,
+}
+
+/// Form for adding a process or editing one's command, cwd and env.
+pub struct ProcessDialog {
+    pub mode: DialogMode,
+    pub name: String,
+    pub command: String,
+    /// The process runs `cmd` without a shell: `command` holds its arguments,
+    /// quoted, and is split again on apply
+    pub argv: bool,
+    pub cwd: String,
+    /// `KEY=value` pairs separated by spaces, quoted like in the shell
+    pub env: String,
+    pub save: bool,
+    pub error: Option<String>,
+    focus: usize,
+    /// A config file was loaded, so changes can be saved to it
+    can_save: bool,
+}
+
+impl ProcessDialog {
+    pub fn add(can_save: bool) -> Self {
+        Self {
+            mode: DialogMode::Add,
+            name: String::new(),
+            command: String::new(),
+            argv: false,
+            cwd: String::new(),
+            env: String::new(),
+            save: false,
+            error: None,
+            focus: 0,
+            can_save,
+        }
+    }
+
+    /// Shows the command, cwd and env as written, before `${VAR}`
+    /// interpolation.
+    pub fn edit(config: &ProcessConfig, can_save: bool) -> Self {
+        let raw = config.raw_command();
+        let (command, argv) = match raw.cmd {
+            Some(ref args) => (shell_words::join(args), true),
+            None => (raw.command.clone(), false),
+        };
+        let mut env: Vec<String> = raw
+            .env
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, shell_words::quote(v)))
+            .collect();
+        env.sort();
+        Self {
+            mode: DialogMode::Edit(config.name.clone()),
+            name: config.name.clone(),
+            command,
+            argv,
+            cwd: raw
+                .cwd
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            env: env.join(" "),
+            save: false,
+            error: None,
+            focus: 0,
+            can_save,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self.mode {
+            DialogMode::Add => " Add process ".to_string(),
+            DialogMode::Edit(ref name) => format!(" Edit {} ", name),
+        }
+    }
+
+    /// Fields in tab order. The name is fixed once a process exists.
+    pub fn fields(&self) -> Vec<DialogField> {
+        let mut fields = Vec::new();
+        if self.mode == DialogMode::Add {
+            fields.push(DialogField::Name);
+        }
+        fields.extend([DialogField::Command, DialogField::Cwd, DialogField::Env]);
+        if self.can_save {
+            fields.push(DialogField::Save);
+        }
+        fields
+    }
+
+    pub fn focused(&self) -> DialogField {
+        let fields = self.fields();
+        fields[self.focus.min(fields.len() - 1)]
+    }
+
+    pub fn next_field(&mut self) {
+        self.focus = (self.focus + 1) % self.fields().len();
+    }
+
+    pub fn prev_field(&mut self) {
+        let len = self.fields().len();
+        self.focus = (self.focus + len - 1) % len;
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        self.error = None;
+        match self.focused() {
+            DialogField::Name => self.name.push(c),
+            DialogField::Command => self.command.push(c),
+            DialogField::Cwd => self.cwd.push(c),
+            DialogField::Env => self.env.push(c),
+            DialogField::Save if c == ' ' => self.save = !self.save,
+            DialogField::Save => {}
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.error = None;
+        match self.focused() {
+            DialogField::Name => self.name.pop(),
+            DialogField::Command => self.command.pop(),
+            DialogField::Cwd => self.cwd.pop(),
+            DialogField::Env => self.env.pop(),
+            DialogField::Save => None,
+        };
+    }
+
+    /// Validate the form and apply it to `base`: a default config when
+    /// adding, the current one when editing. `${VAR}` references are
+    /// resolved like in the config file.
+    pub fn apply(&self, mut base: ProcessConfig) -> Result<ProcessConfig> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            bail!("Name is required");
+        }
+        if name.contains(char::is_whitespace) {
+            bail!("Name can't contain spaces");
+        }
+        let command = self.command.trim();
+        if command.is_empty() {
+            bail!("Command is required");
+        }
+        let env = parse_env(&self.env)?;
+
+        let (command, cmd) = if self.argv {
+            let args = shell_words::split(command)
+                .map_err(|_| anyhow::anyhow!("Unmatched quote in command"))?;
+            (String::new(), Some(args))
+        } else {
+            (command.to_string(), None)
+        };
+
+        base.name = name.to_string();
+        let raw = RawCommand {
+            command,
+            cmd,
+            cwd: match self.cwd.trim() {
+                "" => None,
+                cwd => Some(PathBuf::from(cwd)),
+            },
+            env,
+        };
+        interpolate_config(&mut base, raw)?;
+        Ok(base)
+    }
+}
+
+/// Parse `KEY=value KEY2='value with spaces'`.
+fn parse_env(input: &str) -> Result<HashMap<String, String>> {
+    let pairs = shell_words::split(input).map_err(|_| anyhow::anyhow!("Unmatched quote in env"))?;
+    let mut env = HashMap::new();
+    for pair in pairs {
+        match pair.split_once('=') {
+            Some((key, value)) if !key.is_empty() => {
+                env.insert(key.to_string(), value.to_string());
+            }
+            _ => bail!("Expected KEY=value in env, got \"{}\"", pair),
+        }
+    }
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut dialog = ProcessDialog::add(false);
+        for c in "migrate".chars() {
+            dialog.type_char(c);
+        }
+        assert!(dialog.apply(ProcessConfig::default()).is_err());
+
+        dialog.next_field();
+        for c in "npm run migrate".chars() {
+            dialog.type_char(c);
+        }
+        dialog.next_field();
+        dialog.next_field();
+        for c in "DEBUG=1 URL=postgres://x?a=b".chars() {
+            dialog.type_char(c);
+        }
+        let config = dialog.apply(ProcessConfig::default()).unwrap();
+        assert_eq!(config.name, "migrate");
+        assert_eq!(config.command, "npm run migrate");
+        assert_eq!(config.cwd, None);
+        assert_eq!(config.env["URL"], "postgres://x?a=b");
+        assert_eq!(dialog.fields().len(), 4);
+    }
+
+    #[test]
+    fn test_edit_keeps_name_and_settings() {
+        let base = ProcessConfig {
+            name: "api".to_string(),
+            cmd: Some(vec!["cargo".to_string(), "run".to_string()]),
+            port: Some(8080),
+            ..Default::default()
+        };
+        let mut dialog = ProcessDialog::edit(&base, true);
+        assert_eq!(dialog.focused(), DialogField::Command);
+        assert_eq!(dialog.command, "cargo run");
+        dialog.type_char('s');
+        dialog.prev_field();
+        assert_eq!(dialog.focused(), DialogField::Save);
+        dialog.type_char(' ');
+        assert!(dialog.save);
+
+        let config = dialog.apply(base).unwrap();
+        assert_eq!(
+            config.cmd,
+            Some(vec!["cargo".to_string(), "runs".to_string()])
+        );
+        assert_eq!(config.port, Some(8080));
+    }
+
+    #[test]
+    fn test_edit_round_trip() {
+        let base = ProcessConfig {
+            name: "web".to_string(),
+            cmd: Some(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "echo hi".to_string(),
+            ]),
+            env: [
+                (
+                    "NODE_OPTIONS".to_string(),
+                    "--inspect --max-old-space-size=4096".to_string(),
+                ),
+                ("PAIR".to_string(), "A=x B=y".to_string()),
+                ("QUOTE".to_string(), "it's".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let dialog = ProcessDialog::edit(&base, false);
+        assert_eq!(dialog.command, "sh -c 'echo hi'");
+        let config = dialog.apply(base.clone()).unwrap();
+        assert_eq!(config.cmd, base.cmd);
+        assert_eq!(config.command, "");
+        assert_eq!(config.env, base.env);
+    }
+
+    #[test]
+    fn test_parse_env() {
+        assert!(parse_env("A=1 B").is_err());
+        assert!(parse_env("=1").is_err());
+        assert!(parse_env("A='1").is_err());
+        assert_eq!(parse_env("A= B=x=y").unwrap()["B"], "x=y");
+        assert_eq!(parse_env("A=\"x y\" B=z").unwrap()["A"], "x y");
+    }
+}
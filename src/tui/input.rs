@@ -8,16 +8,21 @@ use super::actions::Action;
 
 pub fn handle_input(event: &Event, app: &App) -> Action {
     match event {
+        Event::Key(key) if app.dialog.is_some() => handle_dialog_keys(key),
         Event::Key(key) => {
-            if matches!(app.active_tab, ActiveTab::PortKiller) {
-                return handle_port_killer_keys(key);
+            match app.active_tab {
+                ActiveTab::PortKiller => return handle_port_killer_keys(key),
+                ActiveTab::AllLogs => return handle_all_logs_keys(key),
+                ActiveTab::Processes => {}
             }
 
             match app.ui_state.scope {
+                Scope::ProcessList if app.ui_state.show_tree => handle_process_tree_keys(key),
                 Scope::ProcessList => handle_process_list_keys(key),
                 Scope::Terminal | Scope::TerminalZoomed => handle_terminal_keys(key),
             }
         }
+        Event::Mouse(_) if app.dialog.is_some() => Action::None,
         Event::Mouse(mouse) => handle_mouse(mouse, app),
         Event::Resize(w, h) => Action::Resize(*w, *h),
         _ => Action::None,
@@ -41,16 +46,78 @@ fn handle_process_list_keys(key: &KeyEvent) -> Action {
         KeyCode::Tab | KeyCode::Enter => Action::FocusTerminal,
         KeyCode::Char('z') => Action::ToggleZoom,
         KeyCode::Char('?') => Action::ToggleKeymap,
+        KeyCode::Char('i') => Action::ToggleDetails,
+        KeyCode::Char('t') => Action::ToggleProcessTree,
+        KeyCode::Char('T') => Action::ToggleTimestamps,
+        KeyCode::Char('l') | KeyCode::F(3) => Action::SwitchToAllLogs,
         KeyCode::Char('`') | KeyCode::F(2) => Action::SwitchToPortKiller,
+        KeyCode::Char('d') => Action::Detach,
+        KeyCode::Char('a') => Action::OpenAddDialog,
+        KeyCode::Char('e') => Action::OpenEditDialog,
+        KeyCode::Char('D') | KeyCode::Delete => Action::RemoveProcess,
         _ => Action::None,
     }
 }
 
-fn handle_port_killer_keys(key: &KeyEvent) -> Action {
-    if key.modifiers.contains(KeyModifiers::CONTROL) {
-        if key.code == KeyCode::Char('c') {
-            return Action::Quit;
+/// The add / edit process dialog takes all keys while it is open.
+fn handle_dialog_keys(key: &KeyEvent) -> Action {
+    match key.code {
+        KeyCode::Esc => Action::DialogCancel,
+        KeyCode::Enter => Action::DialogSubmit,
+        KeyCode::Tab | KeyCode::Down => Action::DialogNextField,
+        KeyCode::BackTab | KeyCode::Up => Action::DialogPrevField,
+        KeyCode::Backspace => Action::DialogBackspace,
+        KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            Action::DialogType(c)
         }
+        _ => Action::None,
+    }
+}
+
+/// While the process tree is open, navigation and x/X act on its entries.
+fn handle_process_tree_keys(key: &KeyEvent) -> Action {
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Action::Quit;
+    }
+
+    match key.code {
+        KeyCode::Char('q') => Action::Quit,
+        KeyCode::Char('j') | KeyCode::Down => Action::SelectNext,
+        KeyCode::Char('k') | KeyCode::Up => Action::SelectPrev,
+        KeyCode::Char('x') => Action::StopProcess,
+        KeyCode::Char('X') => Action::ForceKill,
+        KeyCode::Char('t') | KeyCode::Esc => Action::ToggleProcessTree,
+        KeyCode::Char('?') => Action::ToggleKeymap,
+        _ => Action::None,
+    }
+}
+
+fn handle_all_logs_keys(key: &KeyEvent) -> Action {
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Action::Quit;
+    }
+
+    match key.code {
+        KeyCode::Esc | KeyCode::F(1) | KeyCode::Tab | KeyCode::Char('l') => Action::SwitchToProcesses,
+        KeyCode::Char('`') | KeyCode::F(2) => Action::SwitchToPortKiller,
+        KeyCode::Char('q') => Action::Quit,
+        KeyCode::Char('j') | KeyCode::Down => Action::SelectNext,
+        KeyCode::Char('k') | KeyCode::Up => Action::SelectPrev,
+        KeyCode::Char(' ') => Action::ToggleMergedFilter,
+        KeyCode::Char('o') => Action::SoloMergedFilter,
+        KeyCode::Char('a') => Action::ShowAllMerged,
+        KeyCode::Char('c') => Action::ClearLogs,
+        KeyCode::Char('T') => Action::ToggleTimestamps,
+        KeyCode::PageUp => Action::ScrollUp(10),
+        KeyCode::PageDown => Action::ScrollDown(10),
+        KeyCode::End | KeyCode::Char('G') => Action::ScrollDown(u16::MAX),
+        _ => Action::None,
+    }
+}
+
+fn handle_port_killer_keys(key: &KeyEvent) -> Action {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        return Action::Quit;
     }
 
     match key.code {
@@ -82,11 +149,8 @@ fn handle_terminal_keys(key: &KeyEvent) -> Action {
         return Action::SwitchToPortKiller;
     }
 
-    if key.modifiers.contains(KeyModifiers::CONTROL) {
-        match key.code {
-            KeyCode::Char('a') => return Action::FocusProcessList,
-            _ => {}
-        }
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('a') {
+        return Action::FocusProcessList;
     }
 
     if let Some(bytes) = key_event_to_bytes(key) {
@@ -97,7 +161,7 @@ fn handle_terminal_keys(key: &KeyEvent) -> Action {
 }
 
 fn handle_mouse(mouse: &MouseEvent, app: &App) -> Action {
-    let (term_cols, _) = crossterm::terminal::size().unwrap_or((80, 24));
+    let (term_cols, _) = app.term_size;
     let list_width = term_cols / 4;
 
     match mouse.kind {
@@ -107,7 +171,7 @@ fn handle_mouse(mouse: &MouseEvent, app: &App) -> Action {
 
             // Click on status bar (row 0) — check for tab clicks
             if y == 0 {
-                // The status bar looks like: " betterprocs  X/Y running  [Processes] [Port Killer]"
+                // The status bar looks like: " betterprocs  X/Y running  [Processes] [All Logs] [Port Killer]"
                 // Rather than computing exact spans, find the tab text positions
                 let running = app.process_manager.processes.iter()
                     .filter(|p| p.status.is_running()).count();
@@ -117,25 +181,34 @@ fn handle_mouse(mouse: &MouseEvent, app: &App) -> Action {
                     + 1; // space
                 let processes_start = prefix_len;
                 let processes_end = processes_start + "[Processes]".len();
-                let portkiller_start = processes_end + 1; // space
+                let all_logs_start = processes_end + 1; // space
+                let all_logs_end = all_logs_start + "[All Logs]".len();
+                let portkiller_start = all_logs_end + 1; // space
                 let portkiller_end = portkiller_start + "[Port Killer]".len();
 
                 let col = x as usize;
                 if col >= processes_start && col < processes_end {
                     return Action::SwitchToProcesses;
                 }
+                if col >= all_logs_start && col < all_logs_end {
+                    return Action::SwitchToAllLogs;
+                }
                 if col >= portkiller_start && col <= portkiller_end {
                     return Action::SwitchToPortKiller;
                 }
                 return Action::None;
             }
 
+            if matches!(app.active_tab, ActiveTab::AllLogs) {
+                return Action::None;
+            }
+
             if x < list_width && !matches!(app.ui_state.scope, Scope::TerminalZoomed) {
                 // Click in process list area — always focus it
                 if y >= 2 {
-                    let idx = (y - 2) as usize;
-                    if idx < app.process_manager.process_count() {
-                        return Action::SelectIndex(idx);
+                    let row = (y - 2) as usize;
+                    if row < app.list_rows().len() {
+                        return Action::SelectRow(row);
                     }
                 }
                 // Clicked empty space in process list — just focus it
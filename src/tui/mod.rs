@@ -1,4 +1,5 @@
 pub mod actions;
+pub mod dialog;
 pub mod input;
 pub mod renderer;
 pub mod widgets;
@@ -1,13 +1,22 @@
+use chrono::{DateTime, Local};
 use ratatui::{
     layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table, TableState},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Sparkline, Table,
+        TableState, Wrap,
+    },
     Frame,
 };
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
-use crate::app::{ActiveTab, App, Scope};
-use crate::process::types::ProcessStatus;
+use crate::app::{ActiveTab, App, ListRow, Scope, Toast, TIMESTAMP_GUTTER};
+use crate::process::handle::ProcessHandle;
+use crate::process::types::{ExitInfo, ProcessStatus, StopCause};
+use crate::terminal::merged::MergedLog;
+use crate::tui::dialog::{DialogField, ProcessDialog};
 
 pub fn render(frame: &mut Frame, app: &App) {
     let area = frame.area();
@@ -25,6 +34,109 @@ pub fn render(frame: &mut Frame, app: &App) {
     if app.ui_state.show_keymap {
         render_keymap_bar(frame, chunks[2], app);
     }
+
+    if let Some(ref toast) = app.ui_state.toast {
+        render_toast(frame, chunks[1], toast);
+    }
+
+    if let Some(ref dialog) = app.dialog {
+        render_dialog(frame, area, dialog, app);
+    }
+}
+
+fn render_toast(frame: &mut Frame, area: Rect, toast: &Toast) {
+    let color = if toast.error { Color::Red } else { Color::Green };
+    let max_width = (area.width / 2).max(20).min(area.width);
+    let text_width = max_width.saturating_sub(2).max(1);
+    let lines = (toast.message.chars().count() as u16).div_ceil(text_width).max(1);
+    let width = (toast.message.chars().count() as u16 + 2).min(max_width);
+    let height = (lines + 2).min(area.height);
+    let rect = Rect::new(
+        area.x + area.width - width,
+        area.y + area.height - height,
+        width,
+        height,
+    );
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(color));
+    let text = Paragraph::new(toast.message.as_str())
+        .style(Style::default().fg(color))
+        .wrap(Wrap { trim: false })
+        .block(block);
+    frame.render_widget(Clear, rect);
+    frame.render_widget(text, rect);
+}
+
+fn render_dialog(frame: &mut Frame, area: Rect, dialog: &ProcessDialog, app: &App) {
+    let fields = dialog.fields();
+    let warning = dialog.save as u16;
+    let height = (fields.len() as u16 + 5 + warning).min(area.height);
+    let width = 70.min(area.width);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let block = Block::default()
+        .title(dialog.title())
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let focused = dialog.focused();
+    let mut lines: Vec<Line> = fields
+        .iter()
+        .map(|&field| {
+            let label_style = if field == focused {
+                Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            let cursor = if field == focused { "_" } else { "" };
+            let (label, value) = match field {
+                DialogField::Name => ("Name", format!("{}{}", dialog.name, cursor)),
+                DialogField::Command => ("Command", format!("{}{}", dialog.command, cursor)),
+                DialogField::Cwd => ("Cwd", format!("{}{}", dialog.cwd, cursor)),
+                DialogField::Env => ("Env", format!("{}{}", dialog.env, cursor)),
+                DialogField::Save => {
+                    let path = app
+                        .config_path
+                        .as_ref()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_default();
+                    let check = if dialog.save { "x" } else { " " };
+                    ("Save", format!("[{}] write to {}", check, path))
+                }
+            };
+            Line::from(vec![
+                Span::styled(format!(" {:<8}", label), label_style),
+                Span::raw(value),
+            ])
+        })
+        .collect();
+    lines.push(Line::from(""));
+    if dialog.save {
+        lines.push(Line::from(Span::styled(
+            " Saving rewrites this process's entry; comments in it are lost",
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    lines.push(match dialog.error {
+        Some(ref error) => Line::from(Span::styled(
+            format!(" {}", error),
+            Style::default().fg(Color::Red),
+        )),
+        None => Line::from(Span::styled(
+            " Enter apply · Esc cancel · Tab next field · Env: KEY=value KEY2='a b'",
+            Style::default().fg(Color::DarkGray),
+        )),
+    });
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(Paragraph::new(lines).block(block), popup);
 }
 
 fn render_status_bar(frame: &mut Frame, area: Rect, app: &App) {
@@ -59,6 +171,17 @@ fn render_status_bar(frame: &mut Frame, area: Rect, app: &App) {
             },
         ),
         Span::raw(" "),
+        Span::styled(
+            "[All Logs]",
+            if matches!(app.active_tab, ActiveTab::AllLogs) {
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            },
+        ),
+        Span::raw(" "),
         Span::styled(
             "[Port Killer]",
             if matches!(app.active_tab, ActiveTab::PortKiller) {
@@ -75,10 +198,25 @@ fn render_status_bar(frame: &mut Frame, area: Rect, app: &App) {
 }
 
 fn render_main_area(frame: &mut Frame, area: Rect, app: &App) {
+    if app.process_manager.is_shutting_down() {
+        render_shutdown(frame, area, app);
+        return;
+    }
+
     match app.active_tab {
         ActiveTab::PortKiller => {
             render_port_killer(frame, area, app);
         }
+        ActiveTab::AllLogs => {
+            let chunks = Layout::horizontal([
+                Constraint::Percentage(25),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+            render_merged_filter(frame, chunks[0], app);
+            render_merged_log(frame, chunks[1], app);
+        }
         ActiveTab::Processes => {
             if matches!(app.ui_state.scope, Scope::TerminalZoomed) {
                 render_output_pane(frame, area, app);
@@ -92,7 +230,13 @@ fn render_main_area(frame: &mut Frame, area: Rect, app: &App) {
             .split(area);
 
             render_process_list(frame, chunks[0], app);
-            render_output_pane(frame, chunks[1], app);
+            if app.ui_state.show_tree {
+                render_tree_pane(frame, chunks[1], app);
+            } else if app.ui_state.show_details {
+                render_details_pane(frame, chunks[1], app);
+            } else {
+                render_output_pane(frame, chunks[1], app);
+            }
         }
     }
 }
@@ -119,49 +263,175 @@ fn render_process_list(frame: &mut Frame, area: Rect, app: &App) {
         return;
     }
 
-    let items: Vec<ListItem> = app
+    let rows = app.list_rows();
+    let grouped = rows.iter().any(|r| matches!(r, ListRow::Group(_)));
+    let name_width = app
         .process_manager
         .processes
         .iter()
-        .map(|handle| {
-            let (icon, icon_style) = match &handle.status {
-                ProcessStatus::Running { .. } => (
-                    "●",
-                    Style::default().fg(Color::Green),
-                ),
-                ProcessStatus::Stopped { exit_code: 0, .. } => (
-                    "○",
-                    Style::default().fg(Color::DarkGray),
-                ),
-                ProcessStatus::Stopped { .. } => (
-                    "✗",
-                    Style::default().fg(Color::Yellow),
-                ),
-                ProcessStatus::Crashed { .. } => (
-                    "✗",
-                    Style::default().fg(Color::Red),
-                ),
-                ProcessStatus::NotStarted => (
-                    "◌",
-                    Style::default().fg(Color::DarkGray),
-                ),
-            };
+        .map(|h| h.config.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| match row {
+            ListRow::Group(group) => group_header(app, group),
+            ListRow::Process(index) => {
+                let handle = &app.process_manager.processes[*index];
+                // Grouped processes are indented under their header
+                match (grouped, handle.config.group.is_some()) {
+                    (true, true) => process_list_item(handle, "  ", name_width),
+                    (true, false) => process_list_item(handle, "", name_width + 2),
+                    (false, _) => process_list_item(handle, "", name_width),
+                }
+            }
+        })
+        .collect();
+
+    let mut state = ListState::default().with_selected(Some(app.selected_row(&rows)));
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    frame.render_stateful_widget(list, area, &mut state);
+}
 
-            let status_label = handle.status.label();
+/// Group header row: name and how many of its processes are running.
+fn group_header(app: &App, group: &str) -> ListItem<'static> {
+    let members = app.process_manager.group_indices(group);
+    let running = members
+        .iter()
+        .filter(|&&i| app.process_manager.processes[i].status.is_running())
+        .count();
+    let arrow = if app.ui_state.collapsed_groups.contains(group) {
+        "▸"
+    } else {
+        "▾"
+    };
+    ListItem::new(Line::from(vec![
+        Span::styled(
+            format!("{} {}", arrow, group),
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(" {}/{} running", running, members.len()),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ]))
+}
+
+fn process_list_item<'a>(
+    handle: &'a ProcessHandle,
+    indent: &'a str,
+    name_width: usize,
+) -> ListItem<'a> {
+    let (icon, icon_style) = status_icon(&handle.status);
+
+    // CPU / memory columns, blank while the process is down
+    let usage = if handle.status.is_running() {
+        format!(
+            " {:>4} {:>5}",
+            format_cpu(handle.usage.cpu_percent),
+            format_bytes(handle.usage.rss_bytes)
+        )
+    } else {
+        " ".repeat(11)
+    };
+
+    let status_label = match handle.status.exit() {
+        Some(exit) => format!("{}: {}", handle.status.label(), exit.describe()),
+        None => handle.status.label().to_string(),
+    };
+
+    let mut spans = vec![
+        Span::raw(indent),
+        Span::styled(format!("{} ", icon), icon_style),
+        Span::raw(format!("{:<width$}", handle.config.name, width = name_width)),
+        Span::styled(usage, Style::default().fg(Color::Cyan)),
+        Span::styled(
+            format!(" [{}]", status_label),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ];
+
+    if let Some(up) = handle.uptime() {
+        spans.push(Span::styled(
+            format!(" {}", format_duration(up)),
+            Style::default().fg(Color::DarkGray),
+        ));
+    } else if let Some(ago) = handle.time_since_exit() {
+        spans.push(Span::styled(
+            format!(" {} ago", format_duration(ago)),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if handle.restart_count > 0 {
+        spans.push(Span::styled(
+            format!(" ↻{}", handle.restart_count),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if handle.crash_looping {
+        spans.push(Span::styled(" crash loop", Style::default().fg(Color::Red)));
+    } else if let Some(at) = handle.next_restart {
+        let secs = at.saturating_duration_since(Instant::now()).as_secs_f32().ceil();
+        spans.push(Span::styled(
+            format!(" retry in {}s", secs),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    ListItem::new(Line::from(spans))
+}
 
+/// Stable color per process name for the merged log view.
+fn process_color(name: &str) -> Color {
+    const PALETTE: [Color; 8] = [
+        Color::Cyan,
+        Color::Green,
+        Color::Yellow,
+        Color::Magenta,
+        Color::Blue,
+        Color::LightRed,
+        Color::LightGreen,
+        Color::LightBlue,
+    ];
+    PALETTE[MergedLog::color_index(name, PALETTE.len())]
+}
+
+/// Checklist of processes shown in the merged log.
+fn render_merged_filter(frame: &mut Frame, area: Rect, app: &App) {
+    let block = Block::default()
+        .title(" Show ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let items: Vec<ListItem> = app
+        .merged_filter_names()
+        .into_iter()
+        .map(|name| {
+            let shown = !app.ui_state.merged_hidden.contains(&name);
+            let color = process_color(&name);
             ListItem::new(Line::from(vec![
-                Span::styled(format!("{} ", icon), icon_style),
-                Span::raw(&handle.config.name),
                 Span::styled(
-                    format!(" [{}]", status_label),
+                    if shown { "[x] " } else { "[ ] " },
                     Style::default().fg(Color::DarkGray),
                 ),
+                Span::styled(name, Style::default().fg(color)),
             ]))
         })
         .collect();
 
-    let mut state = ListState::default().with_selected(Some(app.ui_state.selected_process));
-
+    let mut state = ListState::default().with_selected(Some(app.ui_state.merged_cursor));
     let list = List::new(items)
         .block(block)
         .highlight_style(
@@ -174,6 +444,200 @@ fn render_process_list(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_stateful_widget(list, area, &mut state);
 }
 
+/// Every process's output interleaved, one line per row, name first.
+fn render_merged_log(frame: &mut Frame, area: Rect, app: &App) {
+    let hidden = &app.ui_state.merged_hidden;
+    let scroll = app.ui_state.merged_scroll;
+    let title = if scroll > 0 {
+        format!(" All logs (scrolled up {} lines) ", scroll)
+    } else {
+        " All logs ".to_string()
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let name_width = app
+        .process_manager
+        .processes
+        .iter()
+        .map(|h| h.config.name.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let lines: Vec<Line> = app
+        .process_manager
+        .merged
+        .window(hidden, inner.height as usize, scroll)
+        .into_iter()
+        .map(|line| {
+            let mut spans = Vec::new();
+            if app.ui_state.show_timestamps {
+                let time: DateTime<Local> = line.time.into();
+                spans.push(Span::styled(
+                    format!("{} ", time.format("%H:%M:%S")),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            spans.push(Span::styled(
+                format!("{:<width$} | ", line.process, width = name_width),
+                Style::default().fg(process_color(&line.process)),
+            ));
+            spans.push(Span::raw(line.text.clone()));
+            Line::from(spans)
+        })
+        .collect();
+
+    frame.render_widget(Paragraph::new(lines), inner);
+}
+
+fn status_icon(status: &ProcessStatus) -> (&'static str, Style) {
+    match status {
+        ProcessStatus::Running { .. } => (
+            "●",
+            Style::default().fg(Color::Green),
+        ),
+        ProcessStatus::Starting { .. } => (
+            "◐",
+            Style::default().fg(Color::Yellow),
+        ),
+        ProcessStatus::Ready { .. } => (
+            "✔",
+            Style::default().fg(Color::Green),
+        ),
+        ProcessStatus::Unhealthy { .. } => (
+            "♥",
+            Style::default().fg(Color::Red),
+        ),
+        ProcessStatus::Stopping { .. } => (
+            "◑",
+            Style::default().fg(Color::Yellow),
+        ),
+        ProcessStatus::Stopped { exit } if exit.success() || exit.user_initiated() => (
+            "○",
+            Style::default().fg(Color::DarkGray),
+        ),
+        ProcessStatus::Stopped { .. } => (
+            "✗",
+            Style::default().fg(Color::Yellow),
+        ),
+        ProcessStatus::Crashed { .. } => (
+            "✗",
+            Style::default().fg(Color::Red),
+        ),
+        ProcessStatus::NotStarted => (
+            "◌",
+            Style::default().fg(Color::DarkGray),
+        ),
+        ProcessStatus::Waiting => (
+            "◔",
+            Style::default().fg(Color::Yellow),
+        ),
+    }
+}
+
+/// Output pane title details: "(up 5m, started 14:02:11, 2 restarts) "
+/// or "(exit 1 at 14:03:22, 3m ago) ".
+fn run_summary(handle: &ProcessHandle) -> String {
+    let mut parts = Vec::new();
+    if let Some(exit) = handle.status.exit() {
+        parts.push(exit_summary(exit));
+        if let Some(ago) = handle.time_since_exit() {
+            parts.push(format!("{} ago", format_duration(ago)));
+        }
+    } else if let (Some(up), Some(started)) = (handle.uptime(), handle.started_at) {
+        let started: DateTime<Local> = started.into();
+        parts.push(format!("up {}", format_duration(up)));
+        parts.push(format!("started {}", started.format("%H:%M:%S")));
+    }
+    match handle.restart_count {
+        0 => {}
+        1 => parts.push("1 restart".to_string()),
+        n => parts.push(format!("{} restarts", n)),
+    }
+
+    if parts.is_empty() {
+        String::new()
+    } else {
+        format!("({}) ", parts.join(", "))
+    }
+}
+
+/// "3%", "140%"
+fn format_cpu(percent: f64) -> String {
+    format!("{:.0}%", percent)
+}
+
+/// Compact size: "512K", "340M", "4.1G".
+fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+    match bytes {
+        b if b >= GB => format!("{:.1}G", b as f64 / GB as f64),
+        b if b >= MB => format!("{}M", b / MB),
+        b => format!("{}K", b / KB),
+    }
+}
+
+/// Compact duration: "42s", "5m", "2h 5m", "3d 4h".
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86_399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d {}h", secs / 86_400, secs % 86_400 / 3600),
+    }
+}
+
+/// "SIGSEGV at 14:03:22", "exit 0, stopped by user at 14:03:22"
+fn exit_summary(exit: &ExitInfo) -> String {
+    let by = match exit.cause {
+        StopCause::Exited => "",
+        StopCause::User => ", stopped by user",
+        StopCause::Manager => ", stopped by betterprocs",
+    };
+    let at: DateTime<Local> = exit.at.into();
+    format!("{}{} at {}", exit.describe(), by, at.format("%H:%M:%S"))
+}
+
+fn render_shutdown(frame: &mut Frame, area: Rect, app: &App) {
+    let (stage, stages) = app.process_manager.shutdown_progress();
+
+    let block = Block::default()
+        .title(format!(" Shutting down (stage {}/{}) ", stage, stages))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let mut items: Vec<ListItem> = app
+        .process_manager
+        .processes
+        .iter()
+        .map(|handle| {
+            let (icon, icon_style) = status_icon(&handle.status);
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{} ", icon), icon_style),
+                Span::raw(&handle.config.name),
+                Span::styled(
+                    format!(" [{}]", handle.status.label()),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+    items.push(ListItem::new(""));
+    items.push(ListItem::new(Span::styled(
+        "Press q again to force kill everything",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    frame.render_widget(List::new(items).block(block), area);
+}
+
 fn render_output_pane(frame: &mut Frame, area: Rect, app: &App) {
     let focused = matches!(
         app.ui_state.scope,
@@ -184,7 +648,7 @@ fn render_output_pane(frame: &mut Frame, area: Rect, app: &App) {
     let handle = app.process_manager.processes.get(selected);
 
     let title = match handle {
-        Some(h) => format!(" {} - {} ", h.config.name, h.status.label()),
+        Some(h) => format!(" {} - {} ", h.config.name, h.status.label()) + &run_summary(h),
         None => " Output ".to_string(),
     };
 
@@ -197,9 +661,16 @@ fn render_output_pane(frame: &mut Frame, area: Rect, app: &App) {
             Style::default().fg(Color::DarkGray)
         });
 
-    let inner = block.inner(area);
+    let mut inner = block.inner(area);
     frame.render_widget(block, area);
 
+    if let (Some(handle), true) = (handle, app.ui_state.show_timestamps) {
+        render_timestamp_gutter(frame, inner, handle);
+        let gutter = TIMESTAMP_GUTTER.min(inner.width);
+        inner.x += gutter;
+        inner.width -= gutter;
+    }
+
     // Compute selection range in terminal-absolute coordinates
     let selection = compute_selection(app);
     let copy_flash = app.ui_state.copy_flash > 0;
@@ -221,6 +692,173 @@ fn render_output_pane(frame: &mut Frame, area: Rect, app: &App) {
     }
 }
 
+fn render_details_pane(frame: &mut Frame, area: Rect, app: &App) {
+    let Some(handle) = app.process_manager.processes.get(app.ui_state.selected_process) else {
+        frame.render_widget(
+            Block::default().title(" Details ").borders(Borders::ALL),
+            area,
+        );
+        return;
+    };
+
+    let block = Block::default()
+        .title(format!(" {} - details ", handle.config.name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let label = |text: &str| Span::styled(format!("{:<10}", text), Style::default().fg(Color::DarkGray));
+    let mut lines = vec![
+        Line::from(vec![label("Status"), Span::raw(handle.status.label())]),
+        Line::from(vec![
+            label("PID"),
+            Span::raw(handle.status.pid().map(|p| p.to_string()).unwrap_or_else(|| "-".into())),
+        ]),
+        Line::from(vec![label("Command"), Span::raw(handle.config.command.clone())]),
+    ];
+    if let Some(ref cwd) = handle.config.cwd {
+        lines.push(Line::from(vec![label("Cwd"), Span::raw(cwd.display().to_string())]));
+    }
+    lines.push(Line::from(vec![label("Run"), Span::raw(run_summary(handle))]));
+    if let Some(ref log) = handle.config.log {
+        lines.push(Line::from(vec![label("Log"), Span::raw(log.path.display().to_string())]));
+    }
+    lines.push(Line::from(vec![
+        label("CPU"),
+        Span::raw(format_cpu(handle.usage.cpu_percent)),
+    ]));
+    lines.push(Line::from(vec![
+        label("Memory"),
+        Span::raw(format!(
+            "{} (peak {})",
+            format_bytes(handle.usage.rss_bytes),
+            format_bytes(handle.usage.peak_rss_bytes)
+        )),
+    ]));
+
+    let chunks = Layout::vertical([
+        Constraint::Length(lines.len() as u16 + 1),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+    ])
+    .split(inner);
+
+    frame.render_widget(Paragraph::new(lines), chunks[0]);
+    render_history(frame, chunks[1], " CPU ", &handle.usage.cpu_history, Color::Green);
+    render_history(frame, chunks[2], " Memory ", &handle.usage.rss_history, Color::Magenta);
+}
+
+fn render_tree_pane(frame: &mut Frame, area: Rect, app: &App) {
+    let name = app
+        .process_manager
+        .processes
+        .get(app.ui_state.selected_process)
+        .map(|h| h.config.name.as_str())
+        .unwrap_or("");
+    let entries = app.process_tree.entries();
+
+    let block = Block::default()
+        .title(format!(" {} - process tree ({}) ", name, entries.len()))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    if entries.is_empty() {
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                "Process is not running.",
+                Style::default().fg(Color::DarkGray),
+            )),
+            Rect::new(inner.x + 1, inner.y, inner.width.saturating_sub(1), 1),
+        );
+        return;
+    }
+
+    let header = Row::new(vec!["PID", "CPU", "Mem", "Command"]).style(
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    let rows: Vec<Row> = entries
+        .iter()
+        .map(|e| {
+            let indent = if e.depth == 0 {
+                String::new()
+            } else {
+                format!("{}└ ", "  ".repeat(e.depth - 1))
+            };
+            Row::new(vec![
+                e.pid.to_string(),
+                format_cpu(e.cpu_percent),
+                format_bytes(e.rss_bytes),
+                format!("{}{}", indent, e.cmdline),
+            ])
+        })
+        .collect();
+
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(6),
+        Constraint::Length(7),
+        Constraint::Min(20),
+    ];
+
+    let mut state = TableState::default().with_selected(Some(app.process_tree.selected));
+
+    let table = Table::new(rows, widths)
+        .header(header)
+        .row_highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    frame.render_stateful_widget(table, inner, &mut state);
+}
+
+/// Sparkline of the most recent samples that fit the width.
+fn render_history(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    history: &VecDeque<u64>,
+    color: Color,
+) {
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::TOP)
+        .border_style(Style::default().fg(Color::DarkGray));
+    let width = block.inner(area).width as usize;
+    let data: Vec<u64> = history.iter().skip(history.len().saturating_sub(width)).copied().collect();
+    let sparkline = Sparkline::default()
+        .block(block)
+        .data(&data)
+        .style(Style::default().fg(color));
+    frame.render_widget(sparkline, area);
+}
+
+/// Arrival time of each line left of the output; blank for wrapped rows.
+fn render_timestamp_gutter(frame: &mut Frame, area: Rect, handle: &ProcessHandle) {
+    let width = TIMESTAMP_GUTTER.min(area.width);
+    for row in 0..area.height {
+        let Some(time) = handle.screen.row_time(row) else {
+            continue;
+        };
+        let time: DateTime<Local> = time.into();
+        frame.render_widget(
+            Paragraph::new(Span::styled(
+                time.format("%H:%M:%S").to_string(),
+                Style::default().fg(Color::DarkGray),
+            )),
+            Rect::new(area.x, area.y + row, width, 1),
+        );
+    }
+}
+
 /// Normalized selection: (start_row, start_col, end_row, end_col) in absolute terminal coords.
 /// Returns None if no active selection.
 fn compute_selection(app: &App) -> Option<(u16, u16, u16, u16)> {
@@ -447,7 +1085,26 @@ fn vt100_color_to_ratatui(color: vt100::Color) -> Color {
 }
 
 fn render_keymap_bar(frame: &mut Frame, area: Rect, app: &App) {
-    let keys = if matches!(app.active_tab, ActiveTab::PortKiller) {
+    let keys = if app.process_manager.is_shutting_down() {
+        vec![("q", "force kill")]
+    } else if app.dialog.is_some() {
+        vec![
+            ("Enter", "apply"),
+            ("Esc", "cancel"),
+            ("Tab", "next field"),
+            ("Space", "toggle save"),
+        ]
+    } else if matches!(app.active_tab, ActiveTab::AllLogs) {
+        vec![
+            ("j/k", "select"),
+            ("Space", "show/hide"),
+            ("o", "only"),
+            ("a", "all"),
+            ("PgUp/PgDn", "scroll"),
+            ("c", "clear"),
+            ("l", "processes"),
+        ]
+    } else if matches!(app.active_tab, ActiveTab::PortKiller) {
         vec![
             ("0-9", "type port"),
             ("Up/Down", "select"),
@@ -456,17 +1113,53 @@ fn render_keymap_bar(frame: &mut Frame, area: Rect, app: &App) {
             ("Del", "clear"),
             ("`", "processes"),
         ]
+    } else if app.ui_state.show_tree && matches!(app.ui_state.scope, Scope::ProcessList) {
+        vec![
+            ("j/k", "select"),
+            ("x", "SIGTERM"),
+            ("X", "SIGKILL"),
+            ("t", "close tree"),
+        ]
     } else {
         match app.ui_state.scope {
+            Scope::ProcessList if app.ui_state.selected_group.is_some() => vec![
+                ("j/k", "navigate"),
+                ("s", "start group"),
+                ("x", "stop group"),
+                ("X", "kill group"),
+                ("r", "restart group"),
+                ("Enter", "collapse/expand"),
+            ],
+            Scope::ProcessList if app.server_mode => vec![
+                ("q", "quit"),
+                ("d", "detach"),
+                ("j/k", "navigate"),
+                ("s", "start"),
+                ("x", "stop"),
+                ("r", "restart"),
+                ("a", "add"),
+                ("e", "edit"),
+                ("D", "remove"),
+                ("Tab", "terminal"),
+                ("i", "details"),
+                ("l", "all logs"),
+            ],
             Scope::ProcessList => vec![
                 ("q", "quit"),
                 ("j/k", "navigate"),
                 ("s", "start"),
                 ("x", "stop"),
                 ("r", "restart"),
+                ("a", "add"),
+                ("e", "edit"),
+                ("D", "remove"),
                 ("c", "clear"),
                 ("Tab", "terminal"),
                 ("z", "zoom"),
+                ("i", "details"),
+                ("t", "tree"),
+                ("T", "timestamps"),
+                ("l", "all logs"),
 ("`", "ports"),
             ],
             Scope::Terminal | Scope::TerminalZoomed => vec![